@@ -0,0 +1,141 @@
+//! Measure serialized JSON without allocating it.
+
+use std::io;
+
+use serde::ser::Serialize;
+
+use ser::{self, Result};
+
+/// An IO stream that discards its input and counts its length in bytes and
+/// in UTF-16 code units.
+///
+/// The input is expected to be UTF-8, as the output of the serializers is.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CountingWriter {
+    bytes: usize,
+    utf16_len: usize,
+}
+
+impl CountingWriter {
+    /// Creates a writer that has counted nothing yet.
+    #[must_use]
+    pub fn new() -> Self {
+        CountingWriter::default()
+    }
+
+    /// Returns the number of bytes written.
+    #[must_use]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Returns the number of UTF-16 code units the UTF-8 text written so far
+    /// encodes to, which is what `length` gives for a JavaScript string.
+    #[must_use]
+    pub fn utf16_len(&self) -> usize {
+        self.utf16_len
+    }
+}
+
+impl io::Write for CountingWriter {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes += buf.len();
+        for &byte in buf {
+            // Every character starts with a byte that is not a continuation
+            // byte. Characters of four bytes need a surrogate pair.
+            match byte {
+                0x80..=0xBF => {}
+                0xF0..=0xFF => self.utf16_len += 2,
+                _ => self.utf16_len += 1,
+            }
+        }
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the length in bytes of the output of `to_vec`.
+///
+/// ```edition2018
+/// let value = serde_json::json!({"emoji": "😀"});
+/// assert_eq!(serde_json_v8::serialized_len(&value).unwrap(), 16);
+/// assert_eq!(serde_json_v8::serialized_utf16_len(&value).unwrap(), 14);
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see
+/// `ser::KeyPolicy`.
+#[inline]
+pub fn serialized_len<T>(value: &T) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = CountingWriter::new();
+    try!(ser::to_writer(&mut writer, value));
+    Ok(writer.bytes())
+}
+
+/// Returns the length in bytes of the output of `to_vec_pretty`.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see
+/// `ser::KeyPolicy`.
+#[inline]
+pub fn serialized_len_pretty<T>(value: &T) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = CountingWriter::new();
+    try!(ser::to_writer_pretty(&mut writer, value));
+    Ok(writer.bytes())
+}
+
+/// Returns the length in UTF-16 code units of the output of `to_string`,
+/// which is the `length` of `JSON.stringify(value)`.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see
+/// `ser::KeyPolicy`.
+#[inline]
+pub fn serialized_utf16_len<T>(value: &T) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = CountingWriter::new();
+    try!(ser::to_writer(&mut writer, value));
+    Ok(writer.utf16_len())
+}
+
+/// Returns the length in UTF-16 code units of the output of
+/// `to_string_pretty`, which is the `length` of
+/// `JSON.stringify(value, null, 2)`.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see
+/// `ser::KeyPolicy`.
+#[inline]
+pub fn serialized_utf16_len_pretty<T>(value: &T) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = CountingWriter::new();
+    try!(ser::to_writer_pretty(&mut writer, value));
+    Ok(writer.utf16_len())
+}
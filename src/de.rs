@@ -0,0 +1,640 @@
+//! Deserialize JSON data to a Rust data structure.
+
+pub use serde_json::de::*;
+
+use std::char;
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{self, Deserialize, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde_json::error::{Error, Result};
+use serde_json::Value;
+
+use js_string::{JsString, JS_STRING_TOKEN};
+use parse::{Parser, Reader, Str, RECURSION_LIMIT};
+use ser::MAX_SAFE_INTEGER;
+
+/// What a reviver knows about the value it is given beyond the value itself,
+/// like the context argument of the reviver of `JSON.parse`.
+#[derive(Clone, Copy, Debug)]
+pub struct ReviverContext<'a> {
+    source: Option<&'a str>,
+}
+
+impl<'a> ReviverContext<'a> {
+    /// Returns the JSON text the value was parsed from if it is a primitive,
+    /// and `None` for arrays and objects. This recovers the exact digits of
+    /// numbers that do not fit an `f64`.
+    #[must_use]
+    pub fn source(&self) -> Option<&'a str> {
+        self.source
+    }
+
+    /// Returns the value as a `JsString` if it is a string. Unlike the
+    /// `Value`, this keeps lone surrogates such as `"\ud800"`.
+    #[must_use]
+    pub fn js_string(&self) -> Option<JsString> {
+        let source = self.source.filter(|source| source.starts_with('"'))?;
+        match Reader::new(source).parse_str() {
+            Ok(Str::Borrowed(s)) => Some(JsString::from(s)),
+            Ok(Str::Owned(s)) => Some(JsString::from(s)),
+            Ok(Str::Utf16(units)) => Some(JsString::from(units)),
+            Err(_) => None,
+        }
+    }
+}
+
+/// Parse a string of JSON text into a `Value`, passing every value through a
+/// reviver like `JSON.parse(text, reviver)`.
+///
+/// The reviver is called bottom-up with the key the value is held under, the
+/// value and its context. The root value has an empty key and array elements
+/// have their index as key. The returned value takes the place of the parsed
+/// one. Returning `None` deletes an object property and turns an array
+/// element into `null`, the way a hole is written back by `JSON.stringify`.
+/// The result is `None` if the reviver deletes the root value.
+///
+/// As with `JSON.parse`, the reviver is only called once the whole text has
+/// been parsed, and it visits the properties of an object in the order V8
+/// enumerates them: array indices first, in ascending order. A property
+/// appearing several times is visited once, with its last value.
+///
+/// Numbers too large for an `f64`, which `JSON.parse` reads as infinities,
+/// are given to the reviver as `null`, and lone surrogates in strings are
+/// replaced with U+FFFD REPLACEMENT CHARACTER. The context still holds their
+/// source text and, for strings, a lossless `JsString`.
+///
+/// ```edition2018
+/// use serde_json::Value;
+///
+/// let json = r#"{"id": 123456789012345678901234567890, "name": "ann"}"#;
+/// let value = serde_json_v8::from_str_with_reviver(json, |key, value, context| {
+///     match (key, context.source()) {
+///         ("id", Some(source)) => Some(Value::String(source.to_owned())),
+///         _ => Some(value),
+///     }
+/// })
+/// .unwrap();
+/// assert_eq!(
+///     value,
+///     Some(serde_json::json!({"id": "123456789012345678901234567890", "name": "ann"}))
+/// );
+/// ```
+///
+/// # Errors
+///
+/// This function fails if the input is not valid JSON.
+pub fn from_str_with_reviver<F>(s: &str, mut reviver: F) -> Result<Option<Value>>
+where
+    F: FnMut(&str, Value, &ReviverContext) -> Option<Value>,
+{
+    Parser::new(s, |key, value, source| {
+        reviver(key, value, &ReviverContext { source: source })
+    })
+    .parse()
+}
+
+/// Deserialize an instance of type `T` from JSON text in UTF-16 code units,
+/// the representation of JavaScript strings, reading it the way `JSON.parse`
+/// does.
+///
+/// Strings may contain lone surrogates, either as code units of the input or
+/// as escape sequences. Only a `JsString` can hold them.
+///
+/// ```edition2018
+/// use serde_json_v8::JsString;
+///
+/// let json: Vec<u16> = r#"{"emoji":"😀"}"#.encode_utf16().collect();
+/// let value: serde_json::Value = serde_json_v8::from_utf16(&json).unwrap();
+/// assert_eq!(value["emoji"], "😀");
+///
+/// let json = [0x22, 0xd83d, 0x22];
+/// let truncated: JsString = serde_json_v8::from_utf16(&json).unwrap();
+/// assert_eq!(truncated.as_utf16(), &[0xd83d]);
+/// ```
+///
+/// # Errors
+///
+/// This conversion can fail if the input is not valid JSON, or if the
+/// structure of the input does not match the structure expected by `T`.
+pub fn from_utf16<T>(v: &[u16]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let text = try!(decode_utf16(v));
+    from_str_v8(&text)
+}
+
+/// Decodes JSON text from UTF-16, writing lone surrogates as escape
+/// sequences, which the V8 reader turns back into the same code units.
+fn decode_utf16(units: &[u16]) -> Result<String> {
+    use std::fmt::Write;
+
+    let mut text = String::with_capacity(units.len());
+    let mut escaped = false;
+    for c in char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok(c) => {
+                escaped = c == '\\' && !escaped;
+                text.push(c);
+            }
+            Err(err) if !escaped => {
+                let unit = err.unpaired_surrogate();
+                write!(text, "\\u{unit:04x}").expect("writing to a String cannot fail");
+            }
+            Err(_) => return Err(de::Error::custom("invalid escape in JSON text")),
+        }
+    }
+    Ok(text)
+}
+
+/// A deserializer reading JSON the way `JSON.parse` does.
+///
+/// It accepts the same documents as serde_json's `Deserializer`, and also
+/// strings containing lone surrogates, which only a `JsString` can hold.
+pub struct V8Deserializer<'a> {
+    read: Reader<'a>,
+    remaining_depth: usize,
+    js_numbers: bool,
+}
+
+impl<'a> V8Deserializer<'a> {
+    /// Creates a JSON deserializer from a `&str`.
+    #[must_use]
+    pub fn from_str(s: &'a str) -> Self {
+        V8Deserializer {
+            read: Reader::new(s),
+            remaining_depth: RECURSION_LIMIT,
+            js_numbers: false,
+        }
+    }
+
+    /// Sets whether numbers are read as JavaScript numbers: every number is
+    /// rounded to the nearest double, numbers too large for a double become
+    /// infinite and `-0` keeps its sign. Doubles with an integer value in the
+    /// safe integer range are handed to the visitor as integers, which hold
+    /// them exactly, so integer fields still deserialize.
+    ///
+    /// Reading a `Value` this way and writing it back with `to_string` gives
+    /// what `JSON.stringify(JSON.parse(text))` does.
+    ///
+    /// ```edition2018
+    /// use serde::Deserialize;
+    /// use serde_json::Value;
+    /// use serde_json_v8::de::V8Deserializer;
+    ///
+    /// let mut de = V8Deserializer::from_str("[1e400, -0, 12345678901234567891, 7]");
+    /// de.set_js_numbers(true);
+    /// let value = Value::deserialize(&mut de).unwrap();
+    /// assert_eq!(value[1].as_f64().map(f64::is_sign_negative), Some(true));
+    /// assert_eq!(
+    ///     serde_json_v8::to_string(&value).unwrap(),
+    ///     "[null,0,12345678901234567000,7]"
+    /// );
+    /// ```
+    pub fn set_js_numbers(&mut self, js_numbers: bool) {
+        self.js_numbers = js_numbers;
+    }
+
+    /// The `V8Deserializer::end` method should be called after a value has
+    /// been fully deserialized. This allows the `V8Deserializer` to validate
+    /// that the input stream is at the end or that it only has trailing
+    /// whitespace.
+    ///
+    /// # Errors
+    ///
+    /// This fails if anything other than whitespace follows the value.
+    pub fn end(&mut self) -> Result<()> {
+        self.read.end()
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        if self.remaining_depth == 0 {
+            return Err(self.read.error("recursion limit exceeded"));
+        }
+        self.remaining_depth -= 1;
+        self.read.next();
+        Ok(())
+    }
+
+    fn leave(&mut self, close: u8) -> Result<()> {
+        self.remaining_depth += 1;
+        match self.read.peek_token() {
+            Some(byte) if byte == close => {
+                self.read.next();
+                Ok(())
+            }
+            Some(b',') => {
+                self.read.next();
+                match self.read.peek_token() {
+                    Some(byte) if byte == close => Err(self.read.error("trailing comma")),
+                    _ => Err(self.read.error("trailing characters")),
+                }
+            }
+            None if close == b']' => Err(self.read.error("EOF while parsing a list")),
+            None => Err(self.read.error("EOF while parsing an object")),
+            Some(_) => Err(self.read.error("trailing characters")),
+        }
+    }
+
+    // Only safe integers are cast, which fit in 64 bits.
+    #[allow(clippy::cast_possible_truncation)]
+    fn deserialize_number<'de, V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let start = self.read.index();
+        let is_integer = try!(self.read.scan_number());
+        let text = self.read.slice(start);
+        if self.js_numbers {
+            let value: f64 = text.parse().expect("valid number");
+            let is_safe_integer = value.fract() == 0.0
+                && value.abs() <= MAX_SAFE_INTEGER as f64
+                && !(value == 0.0 && value.is_sign_negative());
+            return if !is_safe_integer {
+                visitor.visit_f64(value)
+            } else if value < 0.0 {
+                visitor.visit_i64(value as i64)
+            } else {
+                visitor.visit_u64(value as u64)
+            };
+        }
+        if is_integer {
+            if text.starts_with('-') {
+                if let Ok(value) = text.parse::<i64>() {
+                    if value != 0 {
+                        return visitor.visit_i64(value);
+                    }
+                }
+            } else if let Ok(value) = text.parse::<u64>() {
+                return visitor.visit_u64(value);
+            }
+        }
+        match text.parse::<f64>() {
+            Ok(value) if value.is_finite() => visitor.visit_f64(value),
+            _ => Err(self.read.error("number out of range")),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut V8Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.read.peek_token() {
+            None => Err(self.read.error("EOF while parsing a value")),
+            Some(b'n') => {
+                try!(self.read.parse_ident("null"));
+                visitor.visit_unit()
+            }
+            Some(b't') => {
+                try!(self.read.parse_ident("true"));
+                visitor.visit_bool(true)
+            }
+            Some(b'f') => {
+                try!(self.read.parse_ident("false"));
+                visitor.visit_bool(false)
+            }
+            Some(b'"') => match try!(self.read.parse_str()) {
+                Str::Borrowed(s) => visitor.visit_borrowed_str(s),
+                Str::Owned(s) => visitor.visit_string(s),
+                Str::Utf16(_) => Err(self.read.error("lone surrogate in hex escape")),
+            },
+            Some(b'-' | b'0'..=b'9') => self.deserialize_number(visitor),
+            Some(b'[') => {
+                try!(self.enter());
+                let value = try!(visitor.visit_seq(SeqAccess { de: self, first: true }));
+                try!(self.leave(b']'));
+                Ok(value)
+            }
+            Some(b'{') => {
+                try!(self.enter());
+                let value = try!(visitor.visit_map(MapAccess { de: self, first: true }));
+                try!(self.leave(b'}'));
+                Ok(value)
+            }
+            Some(_) => Err(self.read.error("expected value")),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.read.peek_token() == Some(b'n') {
+            try!(self.read.parse_ident("null"));
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if name == JS_STRING_TOKEN && self.read.peek_token() == Some(b'"') {
+            return match try!(self.read.parse_str()) {
+                Str::Borrowed(s) => {
+                    visitor.visit_newtype_struct(BorrowedStrDeserializer::<Error>::new(s))
+                }
+                Str::Owned(s) => visitor.visit_newtype_struct(s.into_deserializer()),
+                Str::Utf16(units) => {
+                    let mut bytes = Vec::with_capacity(units.len() * 2);
+                    for unit in units {
+                        bytes.extend_from_slice(&unit.to_be_bytes());
+                    }
+                    visitor.visit_byte_buf(bytes)
+                }
+            };
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.read.peek_token() == Some(b'"') {
+            match try!(self.read.parse_str()) {
+                Str::Borrowed(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+                Str::Owned(s) => visitor.visit_byte_buf(s.into_bytes()),
+                Str::Utf16(_) => Err(self.read.error("lone surrogate in hex escape")),
+            }
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.read.peek_token() {
+            Some(b'"') => {
+                let variant = try!(self.read.parse_string());
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            Some(b'{') => {
+                try!(self.enter());
+                let value = try!(visitor.visit_enum(VariantAccess { de: self }));
+                try!(self.leave(b'}'));
+                Ok(value)
+            }
+            None => Err(self.read.error("EOF while parsing a value")),
+            Some(_) => Err(self.read.error("expected value")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct SeqAccess<'a, 'de: 'a> {
+    de: &'a mut V8Deserializer<'de>,
+    first: bool,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.de.read.peek_token() {
+            Some(b']') => return Ok(None),
+            Some(b',') if !self.first => {
+                self.de.read.next();
+                if self.de.read.peek_token() == Some(b']') {
+                    return Err(self.de.read.error("trailing comma"));
+                }
+            }
+            Some(_) if self.first => {}
+            Some(_) => return Err(self.de.read.error("expected `,` or `]`")),
+            None => return Err(self.de.read.error("EOF while parsing a list")),
+        }
+        self.first = false;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+struct MapAccess<'a, 'de: 'a> {
+    de: &'a mut V8Deserializer<'de>,
+    first: bool,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.de.read.peek_token() {
+            Some(b'}') => return Ok(None),
+            Some(b',') if !self.first => {
+                self.de.read.next();
+                if self.de.read.peek_token() == Some(b'}') {
+                    return Err(self.de.read.error("trailing comma"));
+                }
+            }
+            Some(_) if self.first => {}
+            Some(_) => return Err(self.de.read.error("expected `,` or `}`")),
+            None => return Err(self.de.read.error("EOF while parsing an object")),
+        }
+        self.first = false;
+        match self.de.read.peek_token() {
+            Some(b'"') => seed.deserialize(MapKey { de: &mut *self.de }).map(Some),
+            Some(_) => Err(self.de.read.error("key must be a string")),
+            None => Err(self.de.read.error("EOF while parsing a value")),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        if self.de.read.peek_token() == Some(b':') {
+            self.de.read.next();
+            seed.deserialize(&mut *self.de)
+        } else {
+            Err(self.de.read.error("expected `:`"))
+        }
+    }
+}
+
+struct VariantAccess<'a, 'de: 'a> {
+    de: &'a mut V8Deserializer<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantAccess<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = try!(seed.deserialize(MapKey { de: &mut *self.de }));
+        if self.de.read.peek_token() == Some(b':') {
+            self.de.read.next();
+            Ok((variant, self))
+        } else {
+            Err(self.de.read.error("expected `:`"))
+        }
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+/// Only deserializes object keys, which are always strings. Integer keys
+/// are parsed from the string.
+struct MapKey<'a, 'de: 'a> {
+    de: &'a mut V8Deserializer<'de>,
+}
+
+macro_rules! deserialize_integer_key {
+    ($method:ident => $visit:ident) => {
+        fn $method<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'de>,
+        {
+            let key = try!(self.de.read.parse_string());
+            match key.parse() {
+                Ok(integer) => visitor.$visit(integer),
+                Err(_) => visitor.visit_string(key),
+            }
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MapKey<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match try!(self.de.read.parse_str()) {
+            Str::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Str::Owned(s) => visitor.visit_string(s),
+            Str::Utf16(_) => Err(self.de.read.error("lone surrogate in hex escape")),
+        }
+    }
+
+    deserialize_integer_key!(deserialize_i8 => visit_i8);
+    deserialize_integer_key!(deserialize_i16 => visit_i16);
+    deserialize_integer_key!(deserialize_i32 => visit_i32);
+    deserialize_integer_key!(deserialize_i64 => visit_i64);
+    deserialize_integer_key!(deserialize_u8 => visit_u8);
+    deserialize_integer_key!(deserialize_u16 => visit_u16);
+    deserialize_integer_key!(deserialize_u32 => visit_u32);
+    deserialize_integer_key!(deserialize_u64 => visit_u64);
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // Map keys cannot be null.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_enum(self.de, name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Deserialize an instance of type `T` from a string of JSON text, reading
+/// it the way `JSON.parse` does.
+///
+/// ```edition2018
+/// use serde::Deserialize;
+/// use serde_json_v8::JsString;
+///
+/// #[derive(Deserialize)]
+/// struct Message {
+///     id: u32,
+///     text: JsString,
+/// }
+///
+/// let message: Message = serde_json_v8::de::from_str_v8(r#"{"id": 1, "text": "\udc00"}"#).unwrap();
+/// assert_eq!(message.text.as_utf16(), &[0xdc00]);
+/// ```
+///
+/// # Errors
+///
+/// This conversion can fail if the input is not valid JSON, or if the
+/// structure of the input does not match the structure expected by `T`.
+pub fn from_str_v8<'a, T>(s: &'a str) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = V8Deserializer::from_str(s);
+    let value = try!(T::deserialize(&mut de));
+    try!(de.end());
+    Ok(value)
+}
@@ -0,0 +1,138 @@
+//! Hash serialized JSON without buffering it.
+//!
+//! The output of the V8 serializers is fed straight into a hash function, so
+//! that the digest agrees with hashing `JSON.stringify(value)` in JavaScript.
+//!
+//! ```edition2018
+//! use serde_json_v8::digest::Digest;
+//!
+//! /// 64-bit FNV-1a.
+//! struct Fnv(u64);
+//!
+//! impl Digest for Fnv {
+//!     type Output = u64;
+//!
+//!     fn update(&mut self, data: &[u8]) {
+//!         for &byte in data {
+//!             self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x100000001b3);
+//!         }
+//!     }
+//!
+//!     fn finalize(self) -> u64 {
+//!         self.0
+//!     }
+//! }
+//!
+//! let value = serde_json::json!({"b": 1, "a": [0.1]});
+//! let json = serde_json_v8::to_vec(&value).unwrap();
+//! let mut expected = Fnv(0xcbf29ce484222325);
+//! expected.update(&json);
+//! assert_eq!(
+//!     serde_json_v8::to_digest(Fnv(0xcbf29ce484222325), &value).unwrap(),
+//!     expected.finalize()
+//! );
+//! ```
+
+use std::io;
+
+use serde::ser::Serialize;
+
+use ser::{self, Result, Serializer};
+
+/// A hash function computed incrementally.
+///
+/// Implement this for the hasher of your choice, such as `sha2::Sha256`, by
+/// forwarding to its own update and finalize methods.
+pub trait Digest {
+    /// The finished hash.
+    type Output;
+
+    /// Feeds more input into the hash.
+    fn update(&mut self, data: &[u8]);
+
+    /// Completes the hash.
+    fn finalize(self) -> Self::Output;
+}
+
+/// An IO stream feeding everything written to it into a `Digest`.
+#[derive(Clone, Debug, Default)]
+pub struct DigestWriter<D> {
+    digest: D,
+}
+
+impl<D> DigestWriter<D>
+where
+    D: Digest,
+{
+    /// Creates a writer feeding `digest`.
+    pub fn new(digest: D) -> Self {
+        DigestWriter { digest: digest }
+    }
+
+    /// Completes the hash of everything written so far.
+    pub fn finalize(self) -> D::Output {
+        self.digest.finalize()
+    }
+}
+
+impl<D> io::Write for DigestWriter<D>
+where
+    D: Digest,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.digest.update(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.digest.update(buf);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hash the given data structure serialized as JSON, giving the same result
+/// as hashing the output of `to_vec`.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see
+/// `ser::KeyPolicy`.
+#[inline]
+pub fn to_digest<D, T>(digest: D, value: &T) -> Result<D::Output>
+where
+    D: Digest,
+    T: ?Sized + Serialize,
+{
+    let mut writer = DigestWriter::new(digest);
+    try!(ser::to_writer(&mut writer, value));
+    Ok(writer.finalize())
+}
+
+/// Hash the given data structure serialized as canonical JSON (RFC 8785),
+/// giving the same result as hashing the output of `to_canonical_vec`.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, if `T` contains a map with keys that are maps, structs or enum
+/// variants with data, or if `T` contains a number that is not finite. Other
+/// keys are coerced to strings, see `ser::KeyPolicy`.
+#[inline]
+pub fn to_canonical_digest<D, T>(digest: D, value: &T) -> Result<D::Output>
+where
+    D: Digest,
+    T: ?Sized + Serialize,
+{
+    let mut writer = DigestWriter::new(digest);
+    try!(value.serialize(&mut Serializer::canonical(&mut writer)));
+    Ok(writer.finalize())
+}
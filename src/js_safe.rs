@@ -0,0 +1,361 @@
+//! Serde adapters keeping integers exact on their way through JavaScript.
+//!
+//! JavaScript numbers only represent integers exactly up to `2^53 - 1`.
+//! Annotating a field with `#[serde(with = "serde_json_v8::js_safe")]` writes
+//! it as a number when it is in that range and as a string of digits
+//! otherwise. Both forms are accepted when deserializing.
+//!
+//! The adapter supports `u64`, `i64`, `u128` and `i128`, as well as `Option`
+//! and `Vec` of any supported type.
+//!
+//! ```edition2018
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct User {
+//!     #[serde(with = "serde_json_v8::js_safe")]
+//!     id: u64,
+//!     #[serde(with = "serde_json_v8::js_safe")]
+//!     friends: Vec<u64>,
+//!     #[serde(with = "serde_json_v8::js_safe")]
+//!     parent: Option<i64>,
+//! }
+//!
+//! let user = User {
+//!     id: 1 << 60,
+//!     friends: vec![1, u64::max_value()],
+//!     parent: None,
+//! };
+//! let json = serde_json_v8::to_string(&user).unwrap();
+//! assert_eq!(
+//!     json,
+//!     r#"{"id":"1152921504606846976","friends":[1,"18446744073709551615"],"parent":null}"#
+//! );
+//! assert_eq!(serde_json_v8::from_str::<User>(&json).unwrap(), user);
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use ser::MAX_SAFE_INTEGER;
+
+/// Serializes a value as a number when JavaScript can represent it exactly
+/// and as a string otherwise.
+///
+/// # Errors
+///
+/// Serialization fails if the serializer fails to write the value.
+#[inline]
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: ?Sized + JsSafe,
+    S: Serializer,
+{
+    value.serialize_js_safe(serializer)
+}
+
+/// Deserializes a value written by [`serialize`], accepting both numbers and
+/// strings of digits.
+///
+/// [`serialize`]: fn.serialize.html
+///
+/// # Errors
+///
+/// Deserialization fails if the input is neither an integer nor a string of
+/// digits, or if the integer does not fit in `T`.
+#[inline]
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: JsSafe,
+    D: Deserializer<'de>,
+{
+    T::deserialize_js_safe(deserializer)
+}
+
+/// Types supported by the `js_safe` adapter.
+pub trait JsSafe {
+    /// Serializes `self` as a number if it is a safe integer, and as a string
+    /// otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Serialization fails if the serializer fails to write the value.
+    fn serialize_js_safe<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer;
+
+    /// Deserializes a value from either a number or a string of digits.
+    ///
+    /// # Errors
+    ///
+    /// Deserialization fails if the input is neither an integer nor a string
+    /// of digits, or if the integer does not fit in `Self`.
+    fn deserialize_js_safe<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        Self: Sized,
+        D: Deserializer<'de>;
+}
+
+macro_rules! impl_js_safe_integer {
+    ($ty:ident, $is_safe:expr, $serialize:ident) => {
+        impl JsSafe for $ty {
+            #[inline]
+            fn serialize_js_safe<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let is_safe: fn($ty) -> bool = $is_safe;
+                if is_safe(*self) {
+                    serializer.$serialize(*self)
+                } else {
+                    serializer.collect_str(self)
+                }
+            }
+
+            #[inline]
+            fn deserialize_js_safe<'de, D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_any(IntegerVisitor(PhantomData))
+            }
+        }
+    };
+}
+
+impl_js_safe_integer!(u64, |value| value <= MAX_SAFE_INTEGER, serialize_u64);
+impl_js_safe_integer!(
+    i64,
+    |value| value.unsigned_abs() <= MAX_SAFE_INTEGER,
+    serialize_i64
+);
+impl_js_safe_integer!(
+    u128,
+    |value| value <= u128::from(MAX_SAFE_INTEGER),
+    serialize_u128
+);
+impl_js_safe_integer!(
+    i128,
+    |value| value.unsigned_abs() <= u128::from(MAX_SAFE_INTEGER),
+    serialize_i128
+);
+
+impl<T> JsSafe for Option<T>
+where
+    T: JsSafe,
+{
+    #[inline]
+    fn serialize_js_safe<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            Some(ref value) => serializer.serialize_some(&Wrap(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    #[inline]
+    fn deserialize_js_safe<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct OptionVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> Visitor<'de> for OptionVisitor<T>
+        where
+            T: JsSafe,
+        {
+            type Value = Option<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an optional integer")
+            }
+
+            fn visit_none<E>(self) -> Result<Self::Value, E> {
+                Ok(None)
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E> {
+                Ok(None)
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                T::deserialize_js_safe(deserializer).map(Some)
+            }
+        }
+
+        deserializer.deserialize_option(OptionVisitor(PhantomData))
+    }
+}
+
+impl<T> JsSafe for Vec<T>
+where
+    T: JsSafe,
+{
+    #[inline]
+    fn serialize_js_safe<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = try!(serializer.serialize_seq(Some(self.len())));
+        for value in self {
+            try!(seq.serialize_element(&Wrap(value)));
+        }
+        seq.end()
+    }
+
+    #[inline]
+    fn deserialize_js_safe<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct VecVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> Visitor<'de> for VecVisitor<T>
+        where
+            T: JsSafe,
+        {
+            type Value = Vec<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a sequence of integers")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(Wrap(value)) = try!(seq.next_element()) {
+                    values.push(value);
+                }
+                Ok(values)
+            }
+        }
+
+        deserializer.deserialize_seq(VecVisitor(PhantomData))
+    }
+}
+
+/// Routes the serde traits of a nested value through `JsSafe`.
+struct Wrap<T>(T);
+
+impl<T> Serialize for Wrap<&T>
+where
+    T: ?Sized + JsSafe,
+{
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize_js_safe(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Wrap<T>
+where
+    T: JsSafe,
+{
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize_js_safe(deserializer).map(Wrap)
+    }
+}
+
+/// Accepts an integer written either as a number or as a string.
+struct IntegerVisitor<T>(PhantomData<T>);
+
+impl<T> IntegerVisitor<T>
+where
+    T: TryFrom<i128> + TryFrom<u128>,
+{
+    fn convert<N, E>(value: N) -> Result<T, E>
+    where
+        T: TryFrom<N>,
+        N: Copy + fmt::Display,
+        E: de::Error,
+    {
+        T::try_from(value)
+            .map_err(|_| E::custom(format_args!("integer {value} is out of range")))
+    }
+}
+
+impl<T> Visitor<'_> for IntegerVisitor<T>
+where
+    T: TryFrom<i128> + TryFrom<u128> + FromStr,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an integer or a string of digits")
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        Self::convert(i128::from(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        Self::convert(u128::from(value))
+    }
+
+    fn visit_i128<E>(self, value: i128) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        Self::convert(value)
+    }
+
+    fn visit_u128<E>(self, value: u128) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        Self::convert(value)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn visit_f64<E>(self, value: f64) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        // Numbers such as `1e3` are integers even though they are not written
+        // like one. Anything beyond the safe range was already rounded.
+        if value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER as f64 {
+            Self::convert(value as i128)
+        } else {
+            Err(E::invalid_value(de::Unexpected::Float(value), &self))
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        let is_integer = {
+            let digits = value.strip_prefix('-').unwrap_or(value);
+            !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+        };
+        match value.parse() {
+            Ok(value) if is_integer => Ok(value),
+            _ => Err(E::invalid_value(de::Unexpected::Str(value), &self)),
+        }
+    }
+}
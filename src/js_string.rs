@@ -0,0 +1,200 @@
+//! JavaScript strings, which may contain lone surrogates.
+
+use std::char;
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// Name of the newtype struct a `JsString` with lone surrogates serializes
+/// as. The serializers of this crate write its payload as a JSON string and
+/// the V8 deserializer produces it for strings that are not valid Unicode.
+pub(crate) const JS_STRING_TOKEN: &str = "$serde_json_v8::private::JsString";
+
+/// A string as JavaScript represents it: a sequence of UTF-16 code units
+/// which, unlike a Rust `String`, may contain lone surrogates.
+///
+/// `JSON.parse` accepts escape sequences such as `"\ud800"`, and
+/// `JSON.stringify` writes lone surrogates back as escape sequences. A
+/// `JsString` keeps such strings intact through the V8 deserializer and
+/// serializers. Other formats see valid strings as strings. They see other
+/// strings with lone surrogates replaced with U+FFFD REPLACEMENT CHARACTER
+/// if they are human-readable, like serde_json, and as the big-endian bytes
+/// of their code units otherwise.
+///
+/// ```edition2018
+/// use serde_json_v8::JsString;
+///
+/// let truncated: JsString = serde_json_v8::de::from_str_v8(r#""ab\ud83d""#).unwrap();
+/// assert_eq!(truncated.as_utf16(), &[0x61, 0x62, 0xd83d]);
+/// assert_eq!(serde_json_v8::to_string(&truncated).unwrap(), r#""ab\ud83d""#);
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct JsString {
+    units: Vec<u16>,
+}
+
+impl JsString {
+    /// Creates an empty string.
+    #[must_use]
+    pub fn new() -> Self {
+        JsString::default()
+    }
+
+    /// Returns the UTF-16 code units of the string.
+    #[must_use]
+    pub fn as_utf16(&self) -> &[u16] {
+        &self.units
+    }
+
+    /// Unwraps the UTF-16 code units of the string.
+    #[must_use]
+    pub fn into_utf16(self) -> Vec<u16> {
+        self.units
+    }
+
+    /// Returns whether the string contains no lone surrogates, meaning that
+    /// it converts to a Rust `String` without loss.
+    #[must_use]
+    pub fn is_well_formed(&self) -> bool {
+        char::decode_utf16(self.units.iter().copied()).all(|c| c.is_ok())
+    }
+
+    /// Converts the string to a `String`, replacing lone surrogates with
+    /// U+FFFD REPLACEMENT CHARACTER.
+    #[must_use]
+    pub fn to_string_lossy(&self) -> String {
+        String::from_utf16_lossy(&self.units)
+    }
+
+    /// Converts the string to a `String`, or gives it back if it contains
+    /// lone surrogates.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the string is not well-formed.
+    pub fn into_string(self) -> Result<String, Self> {
+        String::from_utf16(&self.units).map_err(|_| self)
+    }
+}
+
+impl<'a> From<&'a str> for JsString {
+    fn from(s: &'a str) -> Self {
+        JsString {
+            units: s.encode_utf16().collect(),
+        }
+    }
+}
+
+impl From<String> for JsString {
+    fn from(s: String) -> Self {
+        JsString::from(s.as_str())
+    }
+}
+
+impl From<Vec<u16>> for JsString {
+    fn from(units: Vec<u16>) -> Self {
+        JsString { units: units }
+    }
+}
+
+impl Serialize for JsString {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match String::from_utf16(&self.units) {
+            Ok(s) => serializer.serialize_str(&s),
+            Err(_) => serializer.serialize_newtype_struct(JS_STRING_TOKEN, &Utf16Bytes(&self.units)),
+        }
+    }
+}
+
+/// Serializes code units as big-endian bytes for the serializers of this
+/// crate, which say they are not human-readable. Human-readable formats,
+/// which do not know the private struct, get a string with lone surrogates
+/// replaced with U+FFFD REPLACEMENT CHARACTER instead.
+struct Utf16Bytes<'a>(&'a [u16]);
+
+impl Serialize for Utf16Bytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            return serializer.serialize_str(&String::from_utf16_lossy(self.0));
+        }
+        let mut bytes = Vec::with_capacity(self.0.len() * 2);
+        for unit in self.0 {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+/// Decodes big-endian UTF-16 code units.
+pub(crate) fn utf16_from_bytes(bytes: &[u8]) -> Option<Vec<u16>> {
+    let pairs = bytes.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    Some(
+        pairs
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect(),
+    )
+}
+
+impl<'de> Deserialize<'de> for JsString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(JS_STRING_TOKEN, JsStringVisitor)
+    }
+}
+
+struct JsStringVisitor;
+
+impl<'de> Visitor<'de> for JsStringVisitor {
+    type Value = JsString;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<JsString, E>
+    where
+        E: de::Error,
+    {
+        Ok(JsString::from(value))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<JsString, E>
+    where
+        E: de::Error,
+    {
+        match utf16_from_bytes(value) {
+            Some(units) => Ok(JsString::from(units)),
+            None => Err(E::invalid_value(de::Unexpected::Bytes(value), &self)),
+        }
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<JsString, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut bytes = Vec::new();
+        while let Some(byte) = try!(seq.next_element()) {
+            bytes.push(byte);
+        }
+        self.visit_bytes(&bytes)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<JsString, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
@@ -0,0 +1,406 @@
+//! Pretty print JSON within a line width, the way prettier formats JSON.
+//!
+//! `PrettyV8Formatter` puts every element of an array and every property of
+//! an object on a line of its own. The functions of this module keep a
+//! container on one line when it fits within the width, and only break it
+//! otherwise:
+//!
+//! ```edition2018
+//! let value = serde_json::json!({"id": 1, "samples": (0..30).collect::<Vec<_>>(), "tags": ["a", "b"]});
+//! assert_eq!(
+//!     serde_json_v8::to_string_with_width(&value, 40).unwrap(),
+//!     r#"{
+//!   "id": 1,
+//!   "samples": [
+//!     0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+//!     11, 12, 13, 14, 15, 16, 17, 18, 19,
+//!     20, 21, 22, 23, 24, 25, 26, 27, 28,
+//!     29
+//!   ],
+//!   "tags": ["a", "b"]
+//! }"#
+//! );
+//! ```
+//!
+//! Like prettier, arrays of more than one number fill each line with as many
+//! numbers as fit, and arrays of several objects, or of several arrays, each
+//! with more than one element are always broken, along with every array and
+//! object around them. Indentation is two spaces.
+//!
+//! Widths are measured in characters, where prettier measures the width
+//! strings take on a terminal. Lines holding wide characters, like CJK or
+//! emoji, may therefore end up wider than prettier would print them, and
+//! lines holding combining marks narrower.
+
+use std::io;
+
+use serde::ser::Serialize;
+
+use ser::{self, Error, ErrorKind, Result};
+
+/// The width prettier uses by default.
+pub const DEFAULT_WIDTH: usize = 80;
+
+const INDENT: &[u8] = b"  ";
+
+/// A value of compact JSON output, with the width it takes on one line.
+struct Node<'a> {
+    kind: Kind<'a>,
+    flat_width: usize,
+    /// Whether this node, or a node within it, must be broken. Like
+    /// prettier's hard breaks, this breaks every enclosing container too.
+    breaks: bool,
+}
+
+enum Kind<'a> {
+    Scalar(&'a str),
+    Array(Vec<Node<'a>>),
+    Object(Vec<(&'a str, Node<'a>)>),
+}
+
+/// Reads the output of the compact serializer. It is valid JSON, but raw
+/// values written verbatim may contain whitespace.
+struct Scanner<'a> {
+    input: &'a str,
+    index: usize,
+}
+
+impl<'a> Scanner<'a> {
+    /// Skips whitespace and returns the next byte, if any.
+    fn peek(&mut self) -> Option<u8> {
+        while let Some(&byte) = self.input.as_bytes().get(self.index) {
+            match byte {
+                b' ' | b'\n' | b'\t' | b'\r' => self.index += 1,
+                _ => return Some(byte),
+            }
+        }
+        None
+    }
+
+    fn node(&mut self) -> Result<Node<'a>> {
+        match self.peek() {
+            Some(b'[') => {
+                self.index += 1;
+                let mut elements = Vec::new();
+                while try!(self.has_next(b']', elements.is_empty())) {
+                    elements.push(try!(self.node()));
+                }
+                let flat_width = match elements.len() {
+                    0 => 2,
+                    n => 2 + elements.iter().map(|e| e.flat_width).sum::<usize>() + 2 * (n - 1),
+                };
+                let breaks = must_break(&elements) || elements.iter().any(|e| e.breaks);
+                Ok(Node {
+                    kind: Kind::Array(elements),
+                    flat_width: flat_width,
+                    breaks: breaks,
+                })
+            }
+            Some(b'{') => {
+                self.index += 1;
+                let mut entries = Vec::new();
+                while try!(self.has_next(b'}', entries.is_empty())) {
+                    let key = try!(self.string());
+                    if self.peek() != Some(b':') {
+                        return Err(invalid_json());
+                    }
+                    self.index += 1;
+                    entries.push((key, try!(self.node())));
+                }
+                // `{ "key": value, "key": value }`
+                let flat_width = match entries.len() {
+                    0 => 2,
+                    n => {
+                        4 + entries
+                            .iter()
+                            .map(|&(key, ref value)| width(key) + 2 + value.flat_width)
+                            .sum::<usize>()
+                            + 2 * (n - 1)
+                    }
+                };
+                let breaks = entries.iter().any(|entry| entry.1.breaks);
+                Ok(Node {
+                    kind: Kind::Object(entries),
+                    flat_width: flat_width,
+                    breaks: breaks,
+                })
+            }
+            Some(b'"') => self.string().map(scalar),
+            Some(_) => {
+                let start = self.index;
+                while let Some(&byte) = self.input.as_bytes().get(self.index) {
+                    match byte {
+                        b',' | b':' | b']' | b'}' | b' ' | b'\n' | b'\t' | b'\r' => break,
+                        _ => self.index += 1,
+                    }
+                }
+                if self.index == start {
+                    return Err(invalid_json());
+                }
+                Ok(scalar(&self.input[start..self.index]))
+            }
+            None => Err(invalid_json()),
+        }
+    }
+
+    /// Consumes the comma before the next element of a container, or the
+    /// bracket closing it, and tells which one it was.
+    fn has_next(&mut self, close: u8, first: bool) -> Result<bool> {
+        match self.peek() {
+            Some(byte) if byte == close => {
+                self.index += 1;
+                Ok(false)
+            }
+            Some(b',') if !first => {
+                self.index += 1;
+                Ok(true)
+            }
+            _ if first => Ok(true),
+            _ => Err(invalid_json()),
+        }
+    }
+
+    fn string(&mut self) -> Result<&'a str> {
+        if self.peek() != Some(b'"') {
+            return Err(invalid_json());
+        }
+        let start = self.index;
+        self.index += 1;
+        loop {
+            match self.input.as_bytes().get(self.index) {
+                Some(b'"') => break,
+                Some(b'\\') => self.index += 2,
+                Some(_) => self.index += 1,
+                None => return Err(invalid_json()),
+            }
+        }
+        self.index += 1;
+        Ok(&self.input[start..self.index])
+    }
+}
+
+fn invalid_json() -> Error {
+    Error::new(ErrorKind::Custom, "raw value is not valid JSON")
+}
+
+fn scalar(text: &str) -> Node<'_> {
+    Node {
+        kind: Kind::Scalar(text),
+        flat_width: width(text),
+        breaks: false,
+    }
+}
+
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+fn is_number(node: &Node) -> bool {
+    match node.kind {
+        Kind::Scalar(text) => text
+            .trim_start_matches('-')
+            .starts_with(|c: char| c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+/// Prettier always breaks an array of several objects, or of several arrays,
+/// when each of them has more than one element.
+fn must_break(elements: &[Node]) -> bool {
+    elements.len() > 1
+        && elements
+            .iter()
+            .all(|element| match (&element.kind, &elements[0].kind) {
+                (Kind::Array(inner), Kind::Array(_)) => inner.len() > 1,
+                (Kind::Object(inner), Kind::Object(_)) => inner.len() > 1,
+                _ => false,
+            })
+}
+
+struct Printer<W> {
+    writer: W,
+    width: usize,
+    column: usize,
+}
+
+impl<W> Printer<W>
+where
+    W: io::Write,
+{
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.column += width(text);
+        self.writer.write_all(text.as_bytes())
+    }
+
+    fn newline(&mut self, indent: usize) -> io::Result<()> {
+        try!(self.writer.write_all(b"\n"));
+        for _ in 0..indent {
+            try!(self.writer.write_all(INDENT));
+        }
+        self.column = indent * INDENT.len();
+        Ok(())
+    }
+
+    fn fits(&self, node: &Node, trailing: usize) -> bool {
+        !node.breaks && self.column + node.flat_width + trailing <= self.width
+    }
+
+    fn flat(&mut self, node: &Node) -> io::Result<()> {
+        match node.kind {
+            Kind::Scalar(text) => self.write(text),
+            Kind::Array(ref elements) => {
+                try!(self.write("["));
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        try!(self.write(", "));
+                    }
+                    try!(self.flat(element));
+                }
+                self.write("]")
+            }
+            Kind::Object(ref entries) if entries.is_empty() => self.write("{}"),
+            Kind::Object(ref entries) => {
+                try!(self.write("{ "));
+                for (i, &(key, ref value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        try!(self.write(", "));
+                    }
+                    try!(self.write(key));
+                    try!(self.write(": "));
+                    try!(self.flat(value));
+                }
+                self.write(" }")
+            }
+        }
+    }
+
+    /// Prints a node followed by `trailing` characters on the same line.
+    fn print(&mut self, node: &Node, indent: usize, trailing: usize) -> io::Result<()> {
+        match node.kind {
+            Kind::Array(ref elements) if !elements.is_empty() && !self.fits(node, trailing) => {
+                try!(self.write("["));
+                if elements.len() > 1 && elements.iter().all(is_number) {
+                    try!(self.fill(elements, indent + 1));
+                } else {
+                    for (i, element) in elements.iter().enumerate() {
+                        let last = i + 1 == elements.len();
+                        try!(self.newline(indent + 1));
+                        try!(self.print(element, indent + 1, usize::from(!last)));
+                        if !last {
+                            try!(self.write(","));
+                        }
+                    }
+                }
+                try!(self.newline(indent));
+                self.write("]")
+            }
+            Kind::Object(ref entries) if !entries.is_empty() && !self.fits(node, trailing) => {
+                try!(self.write("{"));
+                for (i, &(key, ref value)) in entries.iter().enumerate() {
+                    let last = i + 1 == entries.len();
+                    try!(self.newline(indent + 1));
+                    try!(self.write(key));
+                    try!(self.write(": "));
+                    try!(self.print(value, indent + 1, usize::from(!last)));
+                    if !last {
+                        try!(self.write(","));
+                    }
+                }
+                try!(self.newline(indent));
+                self.write("}")
+            }
+            _ => self.flat(node),
+        }
+    }
+
+    /// Prints numbers separated by commas, starting a new line only when the
+    /// next number does not fit on the current one.
+    fn fill(&mut self, elements: &[Node], indent: usize) -> io::Result<()> {
+        try!(self.newline(indent));
+        for (i, element) in elements.iter().enumerate() {
+            let last = i + 1 == elements.len();
+            let trailing = usize::from(!last);
+            if i > 0 {
+                if self.column + 1 + element.flat_width + trailing <= self.width {
+                    try!(self.write(" "));
+                } else {
+                    try!(self.newline(indent));
+                }
+            }
+            try!(self.flat(element));
+            if !last {
+                try!(self.write(","));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serialize the given data structure as JSON into the IO stream, keeping
+/// arrays and objects on one line when they fit within `width` characters.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see
+/// `ser::KeyPolicy`.
+pub fn to_writer_with_width<W, T>(writer: W, value: &T, width: usize) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let compact = try!(ser::to_string(value));
+    let root = try!(Scanner {
+        input: &compact,
+        index: 0,
+    }
+    .node());
+    let mut printer = Printer {
+        writer: writer,
+        width: width,
+        column: 0,
+    };
+    printer.print(&root, 0, 0).map_err(Error::io)
+}
+
+/// Serialize the given data structure as a JSON byte vector, keeping arrays
+/// and objects on one line when they fit within `width` characters.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see
+/// `ser::KeyPolicy`.
+#[inline]
+pub fn to_vec_with_width<T>(value: &T, width: usize) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = Vec::with_capacity(128);
+    try!(to_writer_with_width(&mut writer, value, width));
+    Ok(writer)
+}
+
+/// Serialize the given data structure as a String of JSON, keeping arrays
+/// and objects on one line when they fit within `width` characters.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see
+/// `ser::KeyPolicy`.
+#[inline]
+pub fn to_string_with_width<T>(value: &T, width: usize) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let vec = try!(to_vec_with_width(value, width));
+    let string = unsafe {
+        // We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
+}
@@ -119,7 +119,6 @@
 //! largely automatically.
 //!
 //! ```edition2018
-//! # use serde_derive::{Deserialize, Serialize};
 //! use serde::{Deserialize, Serialize};
 //! use serde_json::Result;
 //!
@@ -242,7 +241,6 @@
 //! such as a File or a TCP stream.
 //!
 //! ```edition2018
-//! # use serde_derive::{Deserialize, Serialize};
 //! use serde::{Deserialize, Serialize};
 //! use serde_json::Result;
 //!
@@ -294,28 +292,27 @@
 //! [`serde-json-core`]: https://japaric.github.io/serde-json-core/serde_json_core/
 
 #![doc(html_root_url = "https://docs.rs/serde_json_v8/0.1.0")]
-#![cfg_attr(feature = "cargo-clippy", allow(renamed_and_removed_lints))]
-#![cfg_attr(feature = "cargo-clippy", deny(clippy, clippy_pedantic))]
+#![deny(clippy::all, clippy::pedantic)]
 // Ignored clippy lints
-#![cfg_attr(feature = "cargo-clippy", allow(deprecated_cfg_attr, doc_markdown))]
+#![allow(clippy::doc_markdown)]
 // Ignored clippy_pedantic lints
-#![cfg_attr(feature = "cargo-clippy", allow(
+#![allow(
     // Deserializer::from_str, into_iter
-    should_implement_trait,
+    clippy::should_implement_trait,
     // integer and float ser/de requires these sorts of casts
-    cast_possible_wrap,
-    cast_precision_loss,
-    cast_sign_loss,
+    clippy::cast_possible_wrap,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss,
     // things are often more readable this way
-    cast_lossless,
-    module_name_repetitions,
-    shadow_unrelated,
-    single_match_else,
-    use_self,
-    zero_prefixed_literal,
+    clippy::cast_lossless,
+    clippy::module_name_repetitions,
+    clippy::shadow_unrelated,
+    clippy::single_match_else,
+    clippy::use_self,
+    clippy::zero_prefixed_literal,
     // we support older compilers
-    redundant_field_names,
-))]
+    clippy::redundant_field_names,
+)]
 #![deny(missing_docs)]
 
 #[macro_use]
@@ -327,12 +324,33 @@ extern crate itoa;
 extern crate ryu_js;
 
 #[doc(inline)]
-pub use serde_json::de::{from_reader, from_slice, from_str, Deserializer, StreamDeserializer};
+pub use self::de::{
+    from_reader, from_slice, from_str, from_str_with_reviver, from_utf16, Deserializer,
+    StreamDeserializer,
+};
+#[doc(inline)]
+pub use self::count::{
+    serialized_len, serialized_len_pretty, serialized_utf16_len, serialized_utf16_len_pretty,
+};
+#[doc(inline)]
+pub use self::digest::{to_canonical_digest, to_digest};
+#[doc(inline)]
+pub use self::js_string::JsString;
+#[doc(inline)]
+pub use self::layout::{to_string_with_width, to_vec_with_width, to_writer_with_width};
+#[doc(inline)]
+pub use self::normalize::{normalize_to_string, normalize_to_vec, normalize_to_writer};
+#[doc(inline)]
+pub use self::raw::RawJson;
 #[doc(inline)]
-pub use serde_json::error::{Error, Result};
+pub use self::ser::{Error, ErrorKind, Result};
 #[doc(inline)]
 pub use self::ser::{
-    to_string, to_string_pretty, to_vec, to_vec_pretty, to_writer, to_writer_pretty, Serializer,
+    to_canonical_string, to_canonical_vec, to_canonical_writer, to_string, to_string_html_safe,
+    to_string_pretty, to_string_with_replacer, to_string_with_space, to_utf16, to_vec,
+    to_vec_html_safe, to_vec_pretty, to_vec_with_replacer, to_vec_with_space, to_writer,
+    to_writer_html_safe, to_writer_pretty, to_writer_utf16, to_writer_with_replacer,
+    to_writer_with_space, Serializer,
 };
 #[doc(inline)]
 pub use self::value::{from_value, to_value, Map, Number, Value};
@@ -351,8 +369,17 @@ macro_rules! try {
 // #[macro_use]
 // mod macros;
 
-pub use serde_json::de;
+pub mod count;
+pub mod de;
+pub mod digest;
 pub use serde_json::error;
+pub mod js_safe;
+mod js_string;
+pub mod layout;
 pub use serde_json::map;
+pub mod normalize;
+mod parse;
+mod raw;
 pub mod ser;
+pub mod syntax;
 pub use serde_json::value;
@@ -0,0 +1,284 @@
+//! Normalize JSON text the way a round trip through JavaScript does.
+//!
+//! The functions of this module write what
+//! `JSON.stringify(JSON.parse(text), null, space)` gives: numbers are rounded
+//! to the nearest double and printed by `Number.prototype.toString`, object
+//! properties are enumerated in V8's order, the last of duplicate keys wins
+//! and strings are escaped the way `JSON.stringify` escapes them.
+//!
+//! ```edition2018
+//! let text = r#"{ "b": 1.50, "2": "é\/", "a": 1e400, "1": -0, "b": [10000000000000000001] }"#;
+//! assert_eq!(
+//!     serde_json_v8::normalize_to_string(text, 0).unwrap(),
+//!     r#"{"1":0,"2":"é/","b":[10000000000000000000],"a":null}"#
+//! );
+//! ```
+//!
+//! Arrays are written as they are read. Only the values of an object are
+//! held in memory until the object ends, as the position and value of each
+//! property are known only then.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io;
+
+use serde_json::error::{Error, Result};
+
+use parse::{Reader, Str, RECURSION_LIMIT};
+use ser::{
+    array_index, format_escaped_str, format_escaped_utf16, Escaping, Formatter, PrettyV8Formatter,
+    Space,
+};
+
+/// A property name. Names with lone surrogates are kept as UTF-16.
+#[derive(Clone, Eq, Hash, PartialEq)]
+enum Name<'a> {
+    Str(Cow<'a, str>),
+    Utf16(Vec<u16>),
+}
+
+impl<'a> From<Str<'a>> for Name<'a> {
+    fn from(s: Str<'a>) -> Self {
+        match s {
+            Str::Borrowed(s) => Name::Str(Cow::Borrowed(s)),
+            Str::Owned(s) => Name::Str(Cow::Owned(s)),
+            Str::Utf16(units) => Name::Utf16(units),
+        }
+    }
+}
+
+struct Normalizer<'a, F> {
+    read: Reader<'a>,
+    remaining_depth: usize,
+    formatter: F,
+}
+
+impl<'a, F> Normalizer<'a, F>
+where
+    F: Formatter,
+{
+    fn value<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        let formatter = &mut self.formatter;
+        match self.read.peek_token() {
+            None => Err(self.read.error("EOF while parsing a value")),
+            Some(b'n') => {
+                try!(self.read.parse_ident("null"));
+                formatter.write_null(writer).map_err(Error::io)
+            }
+            Some(b't') => {
+                try!(self.read.parse_ident("true"));
+                formatter.write_bool(writer, true).map_err(Error::io)
+            }
+            Some(b'f') => {
+                try!(self.read.parse_ident("false"));
+                formatter.write_bool(writer, false).map_err(Error::io)
+            }
+            Some(b'"') => {
+                let result = match try!(self.read.parse_str()) {
+                    Str::Borrowed(s) => format_escaped_str(writer, formatter, s, Escaping::default()),
+                    Str::Owned(s) => format_escaped_str(writer, formatter, &s, Escaping::default()),
+                    Str::Utf16(units) => {
+                        format_escaped_utf16(writer, formatter, &units, Escaping::default())
+                    }
+                };
+                result.map_err(Error::io)
+            }
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.read.index();
+                try!(self.read.scan_number());
+                // Rounding gives infinity for numbers out of range, which
+                // `JSON.stringify` writes as `null`, and its sign is dropped
+                // from negative zero.
+                let value: f64 = self.read.slice(start).parse().expect("valid number");
+                let result = if !value.is_finite() {
+                    formatter.write_null(writer)
+                } else if value == 0.0 {
+                    formatter.write_f64(writer, 0.0)
+                } else {
+                    formatter.write_f64(writer, value)
+                };
+                result.map_err(Error::io)
+            }
+            Some(b'[') => self.array(writer),
+            Some(b'{') => self.object(writer),
+            Some(_) => Err(self.read.error("expected value")),
+        }
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        if self.remaining_depth == 0 {
+            return Err(self.read.error("recursion limit exceeded"));
+        }
+        self.remaining_depth -= 1;
+        self.read.next();
+        Ok(())
+    }
+
+    fn array<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        try!(self.enter());
+        try!(self.formatter.begin_array(writer).map_err(Error::io));
+        if self.read.peek_token() != Some(b']') {
+            let mut first = true;
+            loop {
+                try!(self
+                    .formatter
+                    .begin_array_value(writer, first)
+                    .map_err(Error::io));
+                try!(self.value(writer));
+                try!(self.formatter.end_array_value(writer).map_err(Error::io));
+                first = false;
+                match self.read.peek_token() {
+                    Some(b',') => {}
+                    Some(b']') => break,
+                    None => return Err(self.read.error("EOF while parsing a list")),
+                    Some(_) => return Err(self.read.error("expected `,` or `]`")),
+                }
+                self.read.next();
+                if self.read.peek_token() == Some(b']') {
+                    return Err(self.read.error("trailing comma"));
+                }
+            }
+        }
+        self.read.next();
+        self.remaining_depth += 1;
+        self.formatter.end_array(writer).map_err(Error::io)
+    }
+
+    fn object<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        try!(self.enter());
+        try!(self.formatter.begin_object(writer).map_err(Error::io));
+        let mut entries: Vec<(Name<'a>, Vec<u8>)> = Vec::new();
+        let mut positions: HashMap<Name<'a>, usize> = HashMap::new();
+        if self.read.peek_token() != Some(b'}') {
+            loop {
+                match self.read.peek() {
+                    Some(b'"') => {}
+                    None => return Err(self.read.error("EOF while parsing an object")),
+                    Some(_) => return Err(self.read.error("key must be a string")),
+                }
+                let name = Name::from(try!(self.read.parse_str()));
+                if self.read.peek_token() != Some(b':') {
+                    return Err(self.read.error("expected `:`"));
+                }
+                self.read.next();
+                let mut value = Vec::new();
+                try!(self.value(&mut value));
+                // A duplicate key keeps the position of its first occurrence.
+                if let Some(&i) = positions.get(&name) {
+                    entries[i].1 = value;
+                } else {
+                    positions.insert(name.clone(), entries.len());
+                    entries.push((name, value));
+                }
+                match self.read.peek_token() {
+                    Some(b',') => {}
+                    Some(b'}') => break,
+                    None => return Err(self.read.error("EOF while parsing an object")),
+                    Some(_) => return Err(self.read.error("expected `,` or `}`")),
+                }
+                self.read.next();
+                if self.read.peek_token() == Some(b'}') {
+                    return Err(self.read.error("trailing comma"));
+                }
+            }
+        }
+        self.read.next();
+        self.remaining_depth += 1;
+
+        // Array indices come first in ascending order, the other properties
+        // in the order they were created.
+        entries.sort_by_key(|entry| match entry.0 {
+            Name::Str(ref name) => array_index(name).map_or(u64::from(u32::MAX), u64::from),
+            Name::Utf16(_) => u64::from(u32::MAX),
+        });
+        for (i, (name, value)) in entries.into_iter().enumerate() {
+            try!(self.entry(writer, i == 0, &name, &value).map_err(Error::io));
+        }
+        self.formatter.end_object(writer).map_err(Error::io)
+    }
+
+    fn entry<W>(&mut self, writer: &mut W, first: bool, name: &Name, value: &[u8]) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        let formatter = &mut self.formatter;
+        try!(formatter.begin_object_key(writer, first));
+        try!(match *name {
+            Name::Str(ref name) => format_escaped_str(writer, formatter, name, Escaping::default()),
+            Name::Utf16(ref units) => {
+                format_escaped_utf16(writer, formatter, units, Escaping::default())
+            }
+        });
+        try!(formatter.end_object_key(writer));
+        try!(formatter.begin_object_value(writer));
+        try!(writer.write_all(value));
+        formatter.end_object_value(writer)
+    }
+}
+
+/// Write the normalized form of JSON text into the IO stream: what
+/// `JSON.stringify(JSON.parse(text), null, space)` gives.
+///
+/// # Errors
+///
+/// This fails if `text` is not valid JSON, if it nests arrays and objects
+/// more than 128 levels deep, or if writing fails.
+pub fn normalize_to_writer<'a, W, S>(mut writer: W, text: &str, space: S) -> Result<()>
+where
+    W: io::Write,
+    S: Into<Space<'a>>,
+{
+    let mut normalizer = Normalizer {
+        read: Reader::new(text),
+        remaining_depth: RECURSION_LIMIT,
+        formatter: PrettyV8Formatter::with_space(space.into()),
+    };
+    try!(normalizer.value(&mut writer));
+    normalizer.read.end()
+}
+
+/// Normalize JSON text into a byte vector: what
+/// `JSON.stringify(JSON.parse(text), null, space)` gives.
+///
+/// # Errors
+///
+/// This fails if `text` is not valid JSON, or if it nests arrays and objects
+/// more than 128 levels deep.
+#[inline]
+pub fn normalize_to_vec<'a, S>(text: &str, space: S) -> Result<Vec<u8>>
+where
+    S: Into<Space<'a>>,
+{
+    let mut writer = Vec::with_capacity(text.len());
+    try!(normalize_to_writer(&mut writer, text, space));
+    Ok(writer)
+}
+
+/// Normalize JSON text into a String: what
+/// `JSON.stringify(JSON.parse(text), null, space)` gives.
+///
+/// # Errors
+///
+/// This fails if `text` is not valid JSON, or if it nests arrays and objects
+/// more than 128 levels deep.
+#[inline]
+pub fn normalize_to_string<'a, S>(text: &str, space: S) -> Result<String>
+where
+    S: Into<Space<'a>>,
+{
+    let vec = try!(normalize_to_vec(text, space));
+    let string = unsafe {
+        // We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
+}
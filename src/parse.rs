@@ -0,0 +1,511 @@
+//! The JSON grammar as `JSON.parse` reads it, shared by the deserializer and
+//! by the value parser.
+//!
+//! Unlike serde_json, strings may contain lone surrogates written as escape
+//! sequences, as they can in JavaScript.
+
+use std::collections::hash_map::{Entry, HashMap};
+
+use serde::de;
+use serde_json::error::{Error, Result};
+use serde_json::{Map, Number, Value};
+
+use ser::array_index;
+
+/// How deeply arrays and objects may be nested, matching serde_json.
+pub(crate) const RECURSION_LIMIT: usize = 128;
+
+/// The contents of a string token.
+pub(crate) enum Str<'a> {
+    /// A string without escape sequences, borrowed from the input.
+    Borrowed(&'a str),
+    Owned(String),
+    /// A string containing lone surrogates, as UTF-16 code units.
+    Utf16(Vec<u16>),
+}
+
+/// A cursor over JSON text.
+pub(crate) struct Reader<'a> {
+    input: &'a str,
+    index: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Reader {
+            input: input,
+            index: 0,
+        }
+    }
+
+    pub fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.index).copied()
+    }
+
+    pub fn next(&mut self) -> Option<u8> {
+        let byte = self.peek();
+        if byte.is_some() {
+            self.index += 1;
+        }
+        byte
+    }
+
+    pub fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.index += 1;
+        }
+    }
+
+    pub fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.index += 1;
+        }
+    }
+
+    /// Skips whitespace and returns the next byte without consuming it.
+    pub fn peek_token(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.peek()
+    }
+
+    /// Returns the input from `start` up to the current position.
+    pub fn slice(&self, start: usize) -> &'a str {
+        &self.input[start..self.index]
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Builds an error pointing at the current position.
+    pub fn error(&self, msg: &str) -> Error {
+        let consumed = &self.input[..self.index];
+        let line = consumed.matches('\n').count() + 1;
+        let column = match consumed.rfind('\n') {
+            Some(newline) => self.index - newline,
+            None => self.index + 1,
+        };
+        de::Error::custom(format_args!("{msg} at line {line} column {column}"))
+    }
+
+    /// Checks that only whitespace is left.
+    pub fn end(&mut self) -> Result<()> {
+        self.skip_whitespace();
+        if self.index < self.input.len() {
+            Err(self.error("trailing characters"))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn parse_ident(&mut self, ident: &str) -> Result<()> {
+        if self.input[self.index..].starts_with(ident) {
+            self.index += ident.len();
+            Ok(())
+        } else {
+            Err(self.error("expected value"))
+        }
+    }
+
+    /// Skips over a number, returning whether it is written as an integer.
+    pub fn scan_number(&mut self) -> Result<bool> {
+        self.eat(b'-');
+        match self.next() {
+            Some(b'0') => {
+                // There can be only one leading '0'.
+                if let Some(b'0'..=b'9') = self.peek() {
+                    return Err(self.error("invalid number"));
+                }
+            }
+            Some(b'1'..=b'9') => self.eat_digits(),
+            _ => return Err(self.error("invalid number")),
+        }
+        let mut is_integer = true;
+        if self.eat(b'.') {
+            is_integer = false;
+            match self.next() {
+                Some(b'0'..=b'9') => self.eat_digits(),
+                _ => return Err(self.error("invalid number")),
+            }
+        }
+        if self.eat(b'e') || self.eat(b'E') {
+            is_integer = false;
+            if !self.eat(b'+') {
+                self.eat(b'-');
+            }
+            match self.next() {
+                Some(b'0'..=b'9') => self.eat_digits(),
+                _ => return Err(self.error("invalid number")),
+            }
+        }
+        Ok(is_integer)
+    }
+
+    /// Parses a number, giving `None` if it is too large for an `f64`, which
+    /// `JSON.parse` reads as an infinity.
+    pub fn parse_number(&mut self) -> Result<Option<Number>> {
+        let start = self.index;
+        let is_integer = try!(self.scan_number());
+        let text = self.slice(start);
+        if is_integer {
+            // Like serde_json, keep integers exact when they fit and let `-0`
+            // become a float so that its sign survives.
+            if text.starts_with('-') {
+                if let Ok(value) = text.parse::<i64>() {
+                    if value != 0 {
+                        return Ok(Some(Number::from(value)));
+                    }
+                }
+            } else if let Ok(value) = text.parse::<u64>() {
+                return Ok(Some(Number::from(value)));
+            }
+        }
+        // The grammar was checked above, so parsing only fails to give a
+        // finite number when it overflows.
+        Ok(text.parse().ok().and_then(Number::from_f64))
+    }
+
+    /// Parses a string token, starting at its opening quote.
+    pub fn parse_str(&mut self) -> Result<Str<'a>> {
+        self.index += 1;
+        let mut string = String::new();
+        let mut units: Option<Vec<u16>> = None;
+        let mut has_escapes = false;
+        loop {
+            let start = self.index;
+            while let Some(byte) = self.peek() {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+                self.index += 1;
+            }
+            // The loop above only stops at ASCII bytes, which are always
+            // character boundaries.
+            let run = self.slice(start);
+            match units {
+                Some(ref mut units) => units.extend(run.encode_utf16()),
+                None => string.push_str(run),
+            }
+            match self.next() {
+                None => return Err(self.error("EOF while parsing a string")),
+                Some(b'"') => {
+                    return Ok(match units {
+                        Some(units) => Str::Utf16(units),
+                        None if has_escapes => Str::Owned(string),
+                        None => Str::Borrowed(run),
+                    });
+                }
+                Some(b'\\') => {
+                    has_escapes = true;
+                    match try!(self.parse_escape()) {
+                        Ok(c) => match units {
+                            Some(ref mut units) => {
+                                units.extend(c.encode_utf16(&mut [0; 2]).iter());
+                            }
+                            None => string.push(c),
+                        },
+                        Err(surrogate) => {
+                            let units = units.get_or_insert_with(|| string.encode_utf16().collect());
+                            units.push(surrogate);
+                        }
+                    }
+                }
+                Some(_) => {
+                    self.index -= 1;
+                    return Err(self.error(
+                        "control character (\\u0000-\\u001F) found while parsing a string",
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Parses an escape sequence after its backslash, giving either a
+    /// character or a lone surrogate.
+    fn parse_escape(&mut self) -> Result<::std::result::Result<char, u16>> {
+        let c = match self.next() {
+            None => return Err(self.error("EOF while parsing a string")),
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\x08',
+            Some(b'f') => '\x0c',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                let unit = try!(self.parse_hex4());
+                match unit {
+                    0xD800..=0xDBFF => {
+                        // Only combine with the next escape if it completes
+                        // the pair. Otherwise it is read on its own.
+                        let rest = &self.input[self.index..];
+                        let low = if rest.starts_with("\\u") {
+                            u16::from_str_radix(rest.get(2..6).unwrap_or(""), 16).ok()
+                        } else {
+                            None
+                        };
+                        match low {
+                            Some(low @ 0xDC00..=0xDFFF) => {
+                                self.index += 6;
+                                let code_point = 0x10000
+                                    + ((u32::from(unit) - 0xD800) << 10)
+                                    + (u32::from(low) - 0xDC00);
+                                match ::std::char::from_u32(code_point) {
+                                    Some(c) => c,
+                                    None => return Err(self.error("invalid unicode code point")),
+                                }
+                            }
+                            _ => return Ok(Err(unit)),
+                        }
+                    }
+                    0xDC00..=0xDFFF => return Ok(Err(unit)),
+                    _ => match ::std::char::from_u32(u32::from(unit)) {
+                        Some(c) => c,
+                        None => return Err(self.error("invalid unicode code point")),
+                    },
+                }
+            }
+            Some(_) => return Err(self.error("invalid escape")),
+        };
+        Ok(Ok(c))
+    }
+
+    // Hex digits are below 16.
+    #[allow(clippy::cast_possible_truncation)]
+    fn parse_hex4(&mut self) -> Result<u16> {
+        let mut unit = 0;
+        for _ in 0..4 {
+            let digit = match self.next() {
+                None => return Err(self.error("EOF while parsing a string")),
+                Some(byte) => match (byte as char).to_digit(16) {
+                    Some(digit) => digit as u16,
+                    None => return Err(self.error("invalid escape")),
+                },
+            };
+            unit = unit * 16 + digit;
+        }
+        Ok(unit)
+    }
+
+    /// Parses a string token into a `String`, rejecting lone surrogates.
+    pub fn parse_string(&mut self) -> Result<String> {
+        match try!(self.parse_str()) {
+            Str::Borrowed(s) => Ok(s.to_owned()),
+            Str::Owned(s) => Ok(s),
+            Str::Utf16(_) => Err(self.error("lone surrogate in hex escape")),
+        }
+    }
+}
+
+/// A parsed value, holding on to the source text of its primitives until it
+/// is revived.
+enum Parsed<'a> {
+    Primitive(Value, &'a str),
+    Array(Vec<Parsed<'a>>),
+    Object(Vec<(String, Parsed<'a>)>),
+}
+
+/// A parser building `Value`s that hands every value to a callback, together
+/// with the source text of primitive values.
+///
+/// Like the reviver of `JSON.parse`, the callback is only called once the
+/// whole input is known to be valid, innermost values first, and with the
+/// properties of an object in the order V8 enumerates them: array indices in
+/// ascending order, then other names in the order they first appear. A name
+/// appearing several times is visited once, with its last value.
+pub(crate) struct Parser<'a, F> {
+    read: Reader<'a>,
+    remaining_depth: usize,
+    visit: F,
+}
+
+impl<'a, F> Parser<'a, F>
+where
+    F: FnMut(&str, Value, Option<&'a str>) -> Option<Value>,
+{
+    /// Creates a parser calling `visit` with the key each value is held
+    /// under, the value itself and, for primitives, its source text. The
+    /// value returned by `visit` replaces the parsed one; `None` removes it.
+    ///
+    /// A `Value` cannot hold everything `JSON.parse` reads. Numbers too large
+    /// for an `f64` are given as `null`, which is how `JSON.stringify` writes
+    /// the infinities they stand for, and lone surrogates in strings are
+    /// replaced with U+FFFD REPLACEMENT CHARACTER. The source text is intact.
+    pub fn new(input: &'a str, visit: F) -> Self {
+        Parser {
+            read: Reader::new(input),
+            remaining_depth: RECURSION_LIMIT,
+            visit: visit,
+        }
+    }
+
+    /// Parses the whole input as a single value, under the empty key.
+    pub fn parse(mut self) -> Result<Option<Value>> {
+        let parsed = try!(self.parse_value());
+        try!(self.read.end());
+        Ok(self.revive("", parsed))
+    }
+
+    /// Checks that the whole input is a single primitive value. Unlike
+    /// `parse`, this does not call the callback.
+    pub fn check_primitive(mut self) -> Result<bool> {
+        match self.read.peek_token() {
+            Some(b'[' | b'{') => return Ok(false),
+            _ => {
+                try!(self.parse_value());
+            }
+        }
+        try!(self.read.end());
+        Ok(true)
+    }
+
+    fn parse_value(&mut self) -> Result<Parsed<'a>> {
+        self.read.skip_whitespace();
+        let start = self.read.index();
+        let value = match self.read.peek() {
+            None => return Err(self.read.error("EOF while parsing a value")),
+            Some(b'n') => {
+                try!(self.read.parse_ident("null"));
+                Value::Null
+            }
+            Some(b't') => {
+                try!(self.read.parse_ident("true"));
+                Value::Bool(true)
+            }
+            Some(b'f') => {
+                try!(self.read.parse_ident("false"));
+                Value::Bool(false)
+            }
+            Some(b'"') => Value::String(try!(self.parse_string())),
+            Some(b'-' | b'0'..=b'9') => match try!(self.read.parse_number()) {
+                Some(number) => Value::Number(number),
+                None => Value::Null,
+            },
+            Some(b'[') => return self.parse_array(),
+            Some(b'{') => return self.parse_object(),
+            Some(_) => return Err(self.read.error("expected value")),
+        };
+        Ok(Parsed::Primitive(value, self.read.slice(start)))
+    }
+
+    /// Parses a string, replacing lone surrogates.
+    fn parse_string(&mut self) -> Result<String> {
+        Ok(match try!(self.read.parse_str()) {
+            Str::Borrowed(s) => s.to_owned(),
+            Str::Owned(s) => s,
+            Str::Utf16(units) => String::from_utf16_lossy(&units),
+        })
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        if self.remaining_depth == 0 {
+            return Err(self.read.error("recursion limit exceeded"));
+        }
+        self.remaining_depth -= 1;
+        self.read.next();
+        Ok(())
+    }
+
+    fn parse_array(&mut self) -> Result<Parsed<'a>> {
+        try!(self.enter());
+        let mut items = Vec::new();
+        self.read.skip_whitespace();
+        if !self.read.eat(b']') {
+            loop {
+                items.push(try!(self.parse_value()));
+                self.read.skip_whitespace();
+                match self.read.next() {
+                    Some(b',') => {}
+                    Some(b']') => break,
+                    None => return Err(self.read.error("EOF while parsing a list")),
+                    Some(_) => return Err(self.read.error("expected `,` or `]`")),
+                }
+                if self.read.peek_token() == Some(b']') {
+                    return Err(self.read.error("trailing comma"));
+                }
+            }
+        }
+        self.remaining_depth += 1;
+        Ok(Parsed::Array(items))
+    }
+
+    fn parse_object(&mut self) -> Result<Parsed<'a>> {
+        try!(self.enter());
+        let mut entries: Vec<(String, Parsed<'a>)> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        self.read.skip_whitespace();
+        if !self.read.eat(b'}') {
+            loop {
+                match self.read.peek() {
+                    Some(b'"') => {}
+                    None => return Err(self.read.error("EOF while parsing an object")),
+                    Some(_) => return Err(self.read.error("key must be a string")),
+                }
+                let key = try!(self.parse_string());
+                self.read.skip_whitespace();
+                if !self.read.eat(b':') {
+                    return Err(self.read.error("expected `:`"));
+                }
+                let value = try!(self.parse_value());
+                // A repeated name keeps its place and takes the last value.
+                match positions.entry(key) {
+                    Entry::Occupied(position) => entries[*position.get()].1 = value,
+                    Entry::Vacant(position) => {
+                        entries.push((position.key().clone(), value));
+                        position.insert(entries.len() - 1);
+                    }
+                }
+                self.read.skip_whitespace();
+                match self.read.next() {
+                    Some(b',') => {}
+                    Some(b'}') => break,
+                    None => return Err(self.read.error("EOF while parsing an object")),
+                    Some(_) => return Err(self.read.error("expected `,` or `}`")),
+                }
+                if self.read.peek_token() == Some(b'}') {
+                    return Err(self.read.error("trailing comma"));
+                }
+            }
+        }
+        self.remaining_depth += 1;
+        entries.sort_by_key(|entry| array_index(&entry.0).map_or(u64::from(u32::MAX), u64::from));
+        Ok(Parsed::Object(entries))
+    }
+
+    /// Hands a parsed value to the callback, after its elements or
+    /// properties.
+    fn revive(&mut self, key: &str, parsed: Parsed<'a>) -> Option<Value> {
+        match parsed {
+            Parsed::Primitive(value, source) => (self.visit)(key, value, Some(source)),
+            Parsed::Array(items) => {
+                let mut values = Vec::with_capacity(items.len());
+                for (index, item) in items.into_iter().enumerate() {
+                    // Like a hole left by `delete` in JavaScript, a removed
+                    // element is written back as `null`.
+                    let value = self.revive(&index.to_string(), item);
+                    values.push(value.unwrap_or(Value::Null));
+                }
+                (self.visit)(key, Value::Array(values), None)
+            }
+            Parsed::Object(entries) => {
+                let mut map = Map::new();
+                for (name, entry) in entries {
+                    if let Some(value) = self.revive(&name, entry) {
+                        map.insert(name, value);
+                    }
+                }
+                (self.visit)(key, Value::Object(map), None)
+            }
+        }
+    }
+}
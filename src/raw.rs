@@ -0,0 +1,92 @@
+//! Pre-serialized JSON primitives, like `JSON.rawJSON` in JavaScript.
+
+use std::fmt::{self, Display};
+
+use serde::de;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::error::{Error, Result};
+
+use parse::Parser;
+use ser::RAW_VALUE_TOKEN;
+
+/// JSON text for a single primitive value that is written out verbatim, like
+/// the objects created by `JSON.rawJSON(text)`.
+///
+/// This keeps numbers such as amounts of money exact even when they do not
+/// fit an `f64`.
+///
+/// ```edition2018
+/// use serde_json_v8::RawJson;
+///
+/// let amount = RawJson::new("123456789012345678901234.56").unwrap();
+/// assert_eq!(
+///     serde_json_v8::to_string(&vec![amount]).unwrap(),
+///     "[123456789012345678901234.56]"
+/// );
+/// assert!(RawJson::new("[1]").is_err());
+/// assert!(RawJson::new(" 1").is_err());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RawJson {
+    text: String,
+}
+
+impl RawJson {
+    /// Wraps `text` after checking that it is the JSON text of a number,
+    /// string, boolean or `null`, without surrounding whitespace.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `text` is not valid JSON, is an array or an object, or
+    /// starts or ends with whitespace.
+    pub fn new<S>(text: S) -> Result<Self>
+    where
+        S: Into<String>,
+    {
+        let text = text.into();
+        let is_whitespace = |c| c == '\t' || c == '\n' || c == '\r' || c == ' ';
+        if text.starts_with(is_whitespace) || text.ends_with(is_whitespace) {
+            return Err(invalid_raw_json());
+        }
+        if try!(Parser::new(&text, |_, value, _| Some(value)).check_primitive()) {
+            Ok(RawJson { text: text })
+        } else {
+            Err(invalid_raw_json())
+        }
+    }
+
+    /// Returns the JSON text.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Unwraps the JSON text.
+    #[must_use]
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+fn invalid_raw_json() -> Error {
+    de::Error::custom("raw JSON must be a single primitive value without surrounding whitespace")
+}
+
+impl Display for RawJson {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+// Serializes like serde_json's `RawValue`, which the serializers of this crate
+// always write verbatim, whatever the `raw_value` feature says.
+impl Serialize for RawJson {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = try!(serializer.serialize_struct(RAW_VALUE_TOKEN, 1));
+        try!(s.serialize_field(RAW_VALUE_TOKEN, &self.text));
+        s.end()
+    }
+}
@@ -1,194 +1,3628 @@
 //! Serialize a Rust data structure into JSON data.
 
-use std::io;
+use std::char;
+use std::cmp::{self, Ordering};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::num::FpCategory;
+use std::str;
 
-use serde_json::error::Result;
-use serde::ser::Serialize;
+use serde::ser::{self, Impossible, Serialize};
+use serde_json;
 
-pub use serde_json::ser::Formatter;
+use js_string::{utf16_from_bytes, JS_STRING_TOKEN};
+use parse::{Reader, Str};
+
+mod error;
+mod replace;
+
+pub use self::error::{Error, ErrorKind, Result};
+pub use self::replace::Replacer;
+pub use serde_json::ser::{CharEscape, Formatter};
 
 /// A structure for serializing Rust values into JSON.
-#[allow(non_snake_case)]
-pub mod Serializer {
-    use super::{io, Formatter, CompactV8Formatter, PrettyV8Formatter};
+///
+/// ```edition2018
+/// use serde::Serialize;
+///
+/// let mut ser = serde_json_v8::Serializer::new(Vec::new());
+/// vec![0.1f64, 1e21].serialize(&mut ser).unwrap();
+/// assert_eq!(ser.into_inner(), b"[0.1,1e+21]");
+/// ```
+pub struct Serializer<W, F = CompactV8Formatter> {
+    writer: Output<W>,
+    formatter: F,
+    number_policy: NumberPolicy,
+    f32_format: F32Format,
+    integer_policy: IntegerPolicy,
+    key_order: KeyOrder,
+    key_policy: KeyPolicy,
+    escaping: Escaping,
+    path: Vec<PathSegment>,
+}
+
+/// How the serializer writes numbers that `JSON.stringify` cannot represent
+/// faithfully: `NaN`, `Infinity`, `-Infinity` and negative zero.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NumberPolicy {
+    /// Write non-finite numbers as `null` and negative zero as `0`, exactly
+    /// like `JSON.stringify`. This is the default.
+    #[default]
+    JsonStringify,
+    /// Fail with an error pointing to the offending value when a number is
+    /// not finite. Negative zero is written as `0`.
+    Error,
+    /// Write the JavaScript literals `NaN`, `Infinity`, `-Infinity` and `-0`.
+    /// The output is not valid JSON but can be evaluated as JavaScript.
+    Preserve,
+    /// Write the strings `"NaN"`, `"Infinity"`, `"-Infinity"` and `"-0"`.
+    String,
+}
+
+/// How the serializer writes `f32` values.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum F32Format {
+    /// Write the value widened to an `f64`, which is what V8 prints for an
+    /// element of a `Float32Array`: `0.1f32` is written as
+    /// `0.10000000149011612`. This is the default.
+    #[default]
+    Widened,
+    /// Write the shortest decimal that rounds back to the same value through
+    /// `Math.fround`: `0.1f32` is written as `0.1`.
+    Shortest,
+}
+
+/// How the serializer writes integers outside of the range JavaScript numbers
+/// represent exactly, `-(2^53 - 1)..=2^53 - 1`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum IntegerPolicy {
+    /// Write every digit, even though `JSON.parse` rounds the number to the
+    /// nearest double. This is the default.
+    #[default]
+    Exact,
+    /// Write the nearest double like `Number(x)` would print it:
+    /// `2^64 - 1` is written as `18446744073709552000`.
+    RoundLikeV8,
+    /// Write unsafe integers as strings such as `"18446744073709551615"`,
+    /// leaving safe integers as numbers.
+    StringifyUnsafe,
+    /// Write unsafe integers as `BigInt` literals such as
+    /// `18446744073709551615n`. The output is not valid JSON but can be
+    /// evaluated as JavaScript.
+    BigInt,
+    /// Fail with an error pointing to the offending value.
+    Error,
+}
+
+/// The order in which the serializer writes object properties.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum KeyOrder {
+    /// Write properties in the order they are serialized. This is the
+    /// default.
+    #[default]
+    AsIs,
+    /// Write properties in V8's enumeration order: array indices, meaning
+    /// canonical integers below `2^32 - 1`, in ascending numeric order,
+    /// followed by the other keys in the order they are serialized.
+    ///
+    /// A `serde_json::Value` only serializes its keys in insertion order
+    /// with the `value_insertion_order` feature; without it they come
+    /// sorted.
+    V8,
+    /// Write properties sorted by the UTF-16 code units of their keys, as
+    /// RFC 8785 requires.
+    Sorted,
+}
+
+/// How the serializer turns map keys that are not strings into property
+/// names.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum KeyPolicy {
+    /// Coerce keys like `String(key)`: numbers are printed the way V8 prints
+    /// them, `true` is written as `"true"`, `None` and `()` as `"null"`, and
+    /// sequences as their elements joined with commas. Maps, structs and enum
+    /// variants with data fail, since `String(key)` would write every one of
+    /// them as `"[object Object]"`. This is the default.
+    #[default]
+    Coerce,
+    /// Coerce every key like `String(key)`, writing maps, structs and enum
+    /// variants with data as `"[object Object]"`. Distinct keys of these
+    /// kinds silently become duplicate property names.
+    CoerceObjects,
+    /// Coerce numbers, booleans, characters, `None` and `()` like
+    /// `KeyPolicy::Coerce`, but fail on sequences, maps, structs and enum
+    /// variants with data.
+    Strict,
+}
+
+/// The largest integer JavaScript numbers represent exactly,
+/// `Number.MAX_SAFE_INTEGER`.
+pub(crate) const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
 
+impl<W> Serializer<W>
+where
+    W: io::Write,
+{
     /// Creates a new JSON serializer.
     #[inline]
-    pub fn new<W>(writer: W) -> serde_json::ser::Serializer<W, CompactV8Formatter>
-        where
-          W: io::Write,
-    {
-        with_formatter(writer, CompactV8Formatter)
+    pub fn new(writer: W) -> Self {
+        Serializer::with_formatter(writer, CompactV8Formatter)
     }
 
+    /// Creates a new JSON serializer whose output can be inlined into an
+    /// HTML `<script>` element. See `set_html_safe`.
+    #[inline]
+    pub fn html_safe(writer: W) -> Self {
+        let mut ser = Serializer::new(writer);
+        ser.set_html_safe(true);
+        ser
+    }
+}
 
+impl<W> Serializer<W, PrettyV8Formatter<'_>>
+where
+    W: io::Write,
+{
     /// Creates a new JSON pretty print serializer.
     #[inline]
-    pub fn pretty<'a, W>(writer: W) -> serde_json::ser::Serializer<W, PrettyV8Formatter<'a>>
-        where
-          W: io::Write,
-    {
-        with_formatter(writer, PrettyV8Formatter::new())
+    pub fn pretty(writer: W) -> Self {
+        Serializer::with_formatter(writer, PrettyV8Formatter::new())
+    }
+}
+
+impl<W> Serializer<W, CanonicalFormatter>
+where
+    W: io::Write,
+{
+    /// Creates a new serializer for the JSON Canonicalization Scheme of RFC
+    /// 8785. Keys are sorted by UTF-16 code units, integers are rounded like
+    /// JavaScript numbers and non-finite numbers are an error.
+    #[inline]
+    pub fn canonical(writer: W) -> Self {
+        let mut ser = Serializer::with_formatter(writer, CanonicalFormatter);
+        ser.set_key_order(KeyOrder::Sorted);
+        ser.set_number_policy(NumberPolicy::Error);
+        ser.set_integer_policy(IntegerPolicy::RoundLikeV8);
+        ser
+    }
+}
+
+impl<'a, W> Serializer<W, JsLiteralFormatter<'a>>
+where
+    W: io::Write,
+{
+    /// Creates a new serializer writing JavaScript literals. When the
+    /// formatter has number literals enabled, the number policy is set to
+    /// `NumberPolicy::Preserve` so that `NaN`, infinities and `-0` are
+    /// written as such, and the integer policy to `IntegerPolicy::BigInt`.
+    #[inline]
+    pub fn js_literal(writer: W, formatter: JsLiteralFormatter<'a>) -> Self {
+        let number_literals = formatter.number_literals;
+        let mut ser = Serializer::with_formatter(writer, formatter);
+        if number_literals {
+            ser.set_number_policy(NumberPolicy::Preserve);
+            ser.set_integer_policy(IntegerPolicy::BigInt);
+        }
+        ser
     }
+}
 
+impl<W, F> Serializer<W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
     /// Creates a new JSON visitor whose output will be written to the writer
     /// specified.
     #[inline]
-    pub fn with_formatter<W, F>(writer: W, formatter: F) -> serde_json::ser::Serializer<W, F>
-        where
-          W: io::Write,
-          F: Formatter,
-    {
-        serde_json::ser::Serializer::with_formatter(writer, formatter)
+    pub fn with_formatter(writer: W, formatter: F) -> Self {
+        Serializer {
+            writer: Output {
+                writer: writer,
+                buffers: Vec::new(),
+            },
+            formatter: formatter,
+            number_policy: NumberPolicy::default(),
+            f32_format: F32Format::default(),
+            integer_policy: IntegerPolicy::default(),
+            key_order: KeyOrder::default(),
+            key_policy: KeyPolicy::default(),
+            escaping: Escaping::default(),
+            path: Vec::new(),
+        }
+    }
+
+    /// Sets how `NaN`, infinities and negative zero are written.
+    ///
+    /// ```edition2018
+    /// use serde::Serialize;
+    /// use serde_json_v8::ser::NumberPolicy;
+    ///
+    /// let mut ser = serde_json_v8::Serializer::new(Vec::new());
+    /// ser.set_number_policy(NumberPolicy::Preserve);
+    /// vec![std::f64::NAN, -0.0].serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), b"[NaN,-0]");
+    /// ```
+    #[inline]
+    pub fn set_number_policy(&mut self, policy: NumberPolicy) {
+        self.number_policy = policy;
+    }
+
+    /// Sets how `f32` values are written.
+    ///
+    /// ```edition2018
+    /// use serde::Serialize;
+    /// use serde_json_v8::ser::F32Format;
+    ///
+    /// let mut ser = serde_json_v8::Serializer::new(Vec::new());
+    /// ser.set_f32_format(F32Format::Shortest);
+    /// 0.1f32.serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), b"0.1");
+    /// ```
+    #[inline]
+    pub fn set_f32_format(&mut self, format: F32Format) {
+        self.f32_format = format;
+    }
+
+    /// Sets how integers outside of the JavaScript safe integer range are
+    /// written.
+    ///
+    /// ```edition2018
+    /// use serde::Serialize;
+    /// use serde_json_v8::ser::IntegerPolicy;
+    ///
+    /// let mut ser = serde_json_v8::Serializer::new(Vec::new());
+    /// ser.set_integer_policy(IntegerPolicy::StringifyUnsafe);
+    /// vec![1u64 << 53, 42].serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), br#"["9007199254740992",42]"#);
+    /// ```
+    #[inline]
+    pub fn set_integer_policy(&mut self, policy: IntegerPolicy) {
+        self.integer_policy = policy;
+    }
+
+    /// Sets the order in which object properties are written. Orders other
+    /// than `KeyOrder::AsIs` buffer the values of each object until all of
+    /// its properties are known.
+    ///
+    /// ```edition2018
+    /// use serde::Serialize;
+    /// use serde_json_v8::ser::KeyOrder;
+    ///
+    /// let mut ser = serde_json_v8::Serializer::new(Vec::new());
+    /// ser.set_key_order(KeyOrder::V8);
+    /// serde_json::json!({"b": 1, "2": 0, "1": 0}).serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), br#"{"1":0,"2":0,"b":1}"#);
+    /// ```
+    #[inline]
+    pub fn set_key_order(&mut self, order: KeyOrder) {
+        self.key_order = order;
+    }
+
+    /// Sets how map keys that are not strings are turned into property
+    /// names.
+    ///
+    /// ```edition2018
+    /// use serde::Serializer as _;
+    /// use serde_json_v8::ser::{ErrorKind, KeyPolicy};
+    ///
+    /// let mut ser = serde_json_v8::Serializer::new(Vec::new());
+    /// (&mut ser).collect_map(vec![(1.0, 0), (1e21, 1)]).unwrap();
+    /// assert_eq!(ser.into_inner(), br#"{"1":0,"1e+21":1}"#);
+    ///
+    /// let mut ser = serde_json_v8::Serializer::new(Vec::new());
+    /// ser.set_key_policy(KeyPolicy::Strict);
+    /// let err = (&mut ser).collect_map(vec![(vec![1, 2], 0)]).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::KeyMustBeAString);
+    /// ```
+    #[inline]
+    pub fn set_key_policy(&mut self, policy: KeyPolicy) {
+        self.key_policy = policy;
+    }
+
+    /// Sets whether every non-ASCII character in strings and object keys is
+    /// written as a `\uXXXX` escape, characters outside of the Basic
+    /// Multilingual Plane as a surrogate pair. `JSON.parse` reads the output
+    /// back into the same strings.
+    ///
+    /// ```edition2018
+    /// use serde::Serialize;
+    ///
+    /// let mut ser = serde_json_v8::Serializer::pretty(Vec::new());
+    /// ser.set_ensure_ascii(true);
+    /// vec!["caf\u{e9}", "\u{1f600}"].serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     ser.into_inner(),
+    ///     br#"[
+    ///   "caf\u00e9",
+    ///   "\ud83d\ude00"
+    /// ]"#.to_vec(),
+    /// );
+    /// ```
+    #[inline]
+    pub fn set_ensure_ascii(&mut self, ensure_ascii: bool) {
+        self.escaping.ascii = ensure_ascii;
+    }
+
+    /// Sets whether `<`, `>`, `&`, U+2028 LINE SEPARATOR and U+2029
+    /// PARAGRAPH SEPARATOR in strings and object keys are written as
+    /// `\uXXXX` escapes. The output then cannot close an HTML `<script>`
+    /// element it is inlined into, and is valid JavaScript even for engines
+    /// predating ES2019, where the two separators end string literals.
+    ///
+    /// ```edition2018
+    /// use serde::Serialize;
+    ///
+    /// let mut ser = serde_json_v8::Serializer::pretty(Vec::new());
+    /// ser.set_html_safe(true);
+    /// vec!["</script>"].serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), b"[\n  \"\\u003c/script\\u003e\"\n]");
+    /// ```
+    #[inline]
+    pub fn set_html_safe(&mut self, html_safe: bool) {
+        self.escaping.html = html_safe;
+    }
+
+    /// Unwrap the `Writer` from the `Serializer`.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer.writer
     }
 }
 
-/// This structure compacts a JSON value with no extra whitespace.
-#[derive(Clone, Debug)]
-pub struct CompactV8Formatter;
+impl<'a, W, F> ser::Serializer for &'a mut Serializer<W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Compound<'a, W, F>;
+    type SerializeTuple = Compound<'a, W, F>;
+    type SerializeTupleStruct = Compound<'a, W, F>;
+    type SerializeTupleVariant = Compound<'a, W, F>;
+    type SerializeMap = Compound<'a, W, F>;
+    type SerializeStruct = Compound<'a, W, F>;
+    type SerializeStructVariant = Compound<'a, W, F>;
 
-impl Formatter for CompactV8Formatter {
     #[inline]
-    fn write_f32<W: ?Sized>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
-        where
-          W: io::Write,
+    fn serialize_bool(self, value: bool) -> Result<()> {
+        self.formatter
+            .write_bool(&mut self.writer, value)
+            .map_err(Error::io)
+    }
+
+    #[inline]
+    fn serialize_i8(self, value: i8) -> Result<()> {
+        self.formatter
+            .write_i8(&mut self.writer, value)
+            .map_err(Error::io)
+    }
+
+    #[inline]
+    fn serialize_i16(self, value: i16) -> Result<()> {
+        self.formatter
+            .write_i16(&mut self.writer, value)
+            .map_err(Error::io)
+    }
+
+    #[inline]
+    fn serialize_i32(self, value: i32) -> Result<()> {
+        self.formatter
+            .write_i32(&mut self.writer, value)
+            .map_err(Error::io)
+    }
+
+    #[inline]
+    fn serialize_i64(self, value: i64) -> Result<()> {
+        if value.unsigned_abs() <= MAX_SAFE_INTEGER {
+            self.formatter
+                .write_i64(&mut self.writer, value)
+                .map_err(Error::io)
+        } else {
+            self.serialize_unsafe_integer(value as f64, itoa::Buffer::new().format(value))
+        }
+    }
+
+    // Only safe integers are cast, which fit in 64 bits.
+    #[allow(clippy::cast_possible_truncation)]
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        if value.unsigned_abs() <= u128::from(MAX_SAFE_INTEGER) {
+            self.formatter
+                .write_i64(&mut self.writer, value as i64)
+                .map_err(Error::io)
+        } else {
+            self.serialize_unsafe_integer(value as f64, &value.to_string())
+        }
+    }
+
+    #[inline]
+    fn serialize_u8(self, value: u8) -> Result<()> {
+        self.formatter
+            .write_u8(&mut self.writer, value)
+            .map_err(Error::io)
+    }
+
+    #[inline]
+    fn serialize_u16(self, value: u16) -> Result<()> {
+        self.formatter
+            .write_u16(&mut self.writer, value)
+            .map_err(Error::io)
+    }
+
+    #[inline]
+    fn serialize_u32(self, value: u32) -> Result<()> {
+        self.formatter
+            .write_u32(&mut self.writer, value)
+            .map_err(Error::io)
+    }
+
+    #[inline]
+    fn serialize_u64(self, value: u64) -> Result<()> {
+        if value <= MAX_SAFE_INTEGER {
+            self.formatter
+                .write_u64(&mut self.writer, value)
+                .map_err(Error::io)
+        } else {
+            self.serialize_unsafe_integer(value as f64, itoa::Buffer::new().format(value))
+        }
+    }
+
+    // Only safe integers are cast, which fit in 64 bits.
+    #[allow(clippy::cast_possible_truncation)]
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        if value <= u128::from(MAX_SAFE_INTEGER) {
+            self.formatter
+                .write_u64(&mut self.writer, value as u64)
+                .map_err(Error::io)
+        } else {
+            self.serialize_unsafe_integer(value as f64, &value.to_string())
+        }
+    }
+
+    #[inline]
+    fn serialize_f32(self, value: f32) -> Result<()> {
+        match value.classify() {
+            FpCategory::Nan | FpCategory::Infinite => self.serialize_special_number(f64::from(value)),
+            FpCategory::Zero if value.is_sign_negative() => {
+                self.serialize_special_number(f64::from(value))
+            }
+            _ => match self.f32_format {
+                F32Format::Widened => self
+                    .formatter
+                    .write_f32(&mut self.writer, value)
+                    .map_err(Error::io),
+                F32Format::Shortest => self
+                    .formatter
+                    .write_f64(&mut self.writer, shortest_f64(value))
+                    .map_err(Error::io),
+            },
+        }
+    }
+
+    #[inline]
+    fn serialize_f64(self, value: f64) -> Result<()> {
+        match value.classify() {
+            FpCategory::Nan | FpCategory::Infinite => self.serialize_special_number(value),
+            FpCategory::Zero if value.is_sign_negative() => {
+                self.serialize_special_number(value)
+            }
+            _ => self
+                .formatter
+                .write_f64(&mut self.writer, value)
+                .map_err(Error::io),
+        }
+    }
+
+    #[inline]
+    fn serialize_char(self, value: char) -> Result<()> {
+        // A char encoded as UTF-8 takes 4 bytes at most.
+        let mut buf = [0; 4];
+        self.serialize_str(value.encode_utf8(&mut buf))
+    }
+
+    #[inline]
+    fn serialize_str(self, value: &str) -> Result<()> {
+        format_escaped_str(&mut self.writer, &mut self.formatter, value, self.escaping).map_err(Error::io)
+    }
+
+    #[inline]
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        use serde::ser::SerializeSeq;
+        let mut seq = try!(self.serialize_seq(Some(value.len())));
+        for byte in value {
+            try!(seq.serialize_element(byte));
+        }
+        seq.end()
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<()> {
+        self.formatter
+            .write_null(&mut self.writer)
+            .map_err(Error::io)
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    /// Serialize newtypes without an object wrapper.
+    #[inline]
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == JS_STRING_TOKEN {
+            value.serialize(VerbatimEmitter(self))
+        } else {
+            value.serialize(self)
+        }
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        try!(self.begin_variant(variant));
+        try!(value.serialize(&mut *self));
+        self.end_variant()
+    }
+
+    #[inline]
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    #[inline]
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
     {
-        let mut buffer = ryu_js::Buffer::new();
-        let s = buffer.format(f64::from(value));
-        writer.write_all(s.as_bytes())
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        try!(self
+            .formatter
+            .begin_array(&mut self.writer)
+            .map_err(Error::io));
+        if len == Some(0) {
+            try!(self
+                .formatter
+                .end_array(&mut self.writer)
+                .map_err(Error::io));
+            Ok(Compound::Map {
+                ser: self,
+                state: State::Empty,
+            })
+        } else {
+            Ok(Compound::Map {
+                ser: self,
+                state: State::First,
+            })
+        }
+    }
+
+    #[inline]
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        try!(self.begin_variant(variant));
+        self.serialize_seq(Some(len))
+    }
+
+    #[inline]
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        try!(self
+            .formatter
+            .begin_object(&mut self.writer)
+            .map_err(Error::io));
+        if len == Some(0) {
+            try!(self
+                .formatter
+                .end_object(&mut self.writer)
+                .map_err(Error::io));
+            Ok(Compound::Map {
+                ser: self,
+                state: State::Empty,
+            })
+        } else if self.key_order == KeyOrder::AsIs {
+            Ok(Compound::Map {
+                ser: self,
+                state: State::First,
+            })
+        } else {
+            Ok(Compound::Ordered {
+                ser: self,
+                entries: Vec::new(),
+            })
+        }
+    }
+
+    #[inline]
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        match name {
+            NUMBER_TOKEN | RAW_VALUE_TOKEN => Ok(Compound::Verbatim { ser: self }),
+            _ => self.serialize_map(Some(len)),
+        }
     }
 
     #[inline]
-    fn write_f64<W: ?Sized>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        try!(self.begin_variant(variant));
+        self.serialize_map(Some(len))
+    }
+
+    fn collect_str<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Display,
+    {
+        use std::fmt::Write;
+
+        struct Adapter<'ser, W: 'ser, F: 'ser> {
+            writer: &'ser mut W,
+            formatter: &'ser mut F,
+            escaping: Escaping,
+            error: Option<io::Error>,
+        }
+
+        impl<W, F> Write for Adapter<'_, W, F>
         where
-          W: io::Write,
+            W: io::Write,
+            F: Formatter,
+        {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                debug_assert!(self.error.is_none());
+                match format_escaped_str_contents(self.writer, self.formatter, s, self.escaping) {
+                    Ok(()) => Ok(()),
+                    Err(err) => {
+                        self.error = Some(err);
+                        Err(fmt::Error)
+                    }
+                }
+            }
+        }
+
+        try!(self
+            .formatter
+            .begin_string(&mut self.writer)
+            .map_err(Error::io));
+        {
+            let mut adapter = Adapter {
+                writer: &mut self.writer,
+                formatter: &mut self.formatter,
+                escaping: self.escaping,
+                error: None,
+            };
+            match write!(adapter, "{value}") {
+                Ok(()) => debug_assert!(adapter.error.is_none()),
+                Err(fmt::Error) => {
+                    return Err(Error::io(adapter.error.expect("there should be an error")));
+                }
+            }
+        }
+        self.formatter
+            .end_string(&mut self.writer)
+            .map_err(Error::io)
+    }
+}
+
+impl<W, F> Serializer<W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    /// Writes an integer outside of the safe integer range according to the
+    /// integer policy, given its exact `digits`.
+    fn serialize_unsafe_integer(&mut self, rounded: f64, digits: &str) -> Result<()> {
+        match self.integer_policy {
+            IntegerPolicy::Exact => self
+                .formatter
+                .write_number_str(&mut self.writer, digits)
+                .map_err(Error::io),
+            IntegerPolicy::RoundLikeV8 => self
+                .formatter
+                .write_f64(&mut self.writer, rounded)
+                .map_err(Error::io),
+            IntegerPolicy::StringifyUnsafe => {
+                try!(self
+                    .formatter
+                    .begin_string(&mut self.writer)
+                    .map_err(Error::io));
+                try!(self
+                    .formatter
+                    .write_string_fragment(&mut self.writer, digits)
+                    .map_err(Error::io));
+                self.formatter
+                    .end_string(&mut self.writer)
+                    .map_err(Error::io)
+            }
+            IntegerPolicy::BigInt => self
+                .formatter
+                .write_number_str(&mut self.writer, &format!("{digits}n"))
+                .map_err(Error::io),
+            IntegerPolicy::Error => Err(self.error(
+                ErrorKind::UnsafeInteger,
+                format_args!("{digits} is outside of the safe integer range"),
+            )),
+        }
+    }
+
+    /// Writes a non-finite number or negative zero according to the number
+    /// policy.
+    fn serialize_special_number(&mut self, value: f64) -> Result<()> {
+        let literal = if value.is_nan() {
+            "NaN"
+        } else if value == f64::INFINITY {
+            "Infinity"
+        } else if value == f64::NEG_INFINITY {
+            "-Infinity"
+        } else {
+            "-0"
+        };
+        match self.number_policy {
+            NumberPolicy::JsonStringify | NumberPolicy::Error if value == 0.0 => self
+                .formatter
+                .write_f64(&mut self.writer, 0.0)
+                .map_err(Error::io),
+            NumberPolicy::JsonStringify => self
+                .formatter
+                .write_null(&mut self.writer)
+                .map_err(Error::io),
+            NumberPolicy::Error => Err(self.error(
+                ErrorKind::NonFiniteNumber,
+                format_args!("{literal} is not a finite number"),
+            )),
+            NumberPolicy::Preserve => self
+                .formatter
+                .write_number_str(&mut self.writer, literal)
+                .map_err(Error::io),
+            NumberPolicy::String => {
+                format_escaped_str(&mut self.writer, &mut self.formatter, literal, self.escaping)
+                    .map_err(Error::io)
+            }
+        }
+    }
+
+    /// Builds an error for the value currently being serialized, mentioning
+    /// where it is located in the document.
+    #[cold]
+    fn error(&self, kind: ErrorKind, msg: fmt::Arguments) -> Error {
+        self.locate(Error::new(kind, msg))
+    }
+
+    /// Records the location of an error raised by the value currently being
+    /// serialized, unless the error already knows it or the value is the
+    /// root, which has no location.
+    #[cold]
+    fn locate(&self, error: Error) -> Error {
+        if self.path.is_empty() {
+            return error;
+        }
+        error.with_path(DisplayPath(&self.path))
+    }
+
+    /// Records the location of the object whose key failed to serialize,
+    /// unless that object is the root.
+    #[cold]
+    fn locate_key(&self, state: &State, error: Error) -> Error {
+        let depth = self.path.len() - usize::from(*state == State::Rest);
+        if depth == 0 {
+            return error;
+        }
+        error.with_path(DisplayPath(&self.path[..depth]))
+    }
+
+    /// Opens the `{"variant":` wrapper used for externally tagged enum
+    /// variants.
+    fn begin_variant(&mut self, variant: &'static str) -> Result<()> {
+        try!(self
+            .formatter
+            .begin_object(&mut self.writer)
+            .map_err(Error::io));
+        try!(self
+            .formatter
+            .begin_object_key(&mut self.writer, true)
+            .map_err(Error::io));
+        try!(format_escaped_str(&mut self.writer, &mut self.formatter, variant, self.escaping).map_err(Error::io));
+        try!(self
+            .formatter
+            .end_object_key(&mut self.writer)
+            .map_err(Error::io));
+        self.path.push(PathSegment::Field(variant));
+        self.formatter
+            .begin_object_value(&mut self.writer)
+            .map_err(Error::io)
+    }
+
+    /// Closes the wrapper opened by `begin_variant`.
+    fn end_variant(&mut self) -> Result<()> {
+        self.path.pop();
+        try!(self
+            .formatter
+            .end_object_value(&mut self.writer)
+            .map_err(Error::io));
+        self.formatter
+            .end_object(&mut self.writer)
+            .map_err(Error::io)
+    }
+}
+
+impl<W, F> Serializer<W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    /// Writes an object key, preceded by a separator unless it is the first.
+    fn serialize_key_str(&mut self, state: &State, key: &str) -> Result<()> {
+        try!(self
+            .formatter
+            .begin_object_key(&mut self.writer, *state == State::First)
+            .map_err(Error::io));
+        try!(format_escaped_str(&mut self.writer, &mut self.formatter, key, self.escaping).map_err(Error::io));
+        self.formatter
+            .end_object_key(&mut self.writer)
+            .map_err(Error::io)
+    }
+
+    /// Returns the serializer turning map keys into property names.
+    fn map_key_serializer(&self) -> MapKeySerializer {
+        MapKeySerializer {
+            policy: self.key_policy,
+            f32_format: self.f32_format,
+            element: false,
+        }
+    }
+
+    /// Records the key of the entry being serialized, replacing the key of
+    /// the previous entry.
+    fn set_key_segment(&mut self, state: &State, segment: PathSegment) {
+        if *state != State::First {
+            self.path.pop();
+        }
+        self.path.push(segment);
+    }
+
+    fn end_array(&mut self, state: &State) -> Result<()> {
+        if *state == State::Rest {
+            self.path.pop();
+        }
+        self.formatter
+            .end_array(&mut self.writer)
+            .map_err(Error::io)
+    }
+
+    fn end_object(&mut self, state: &State) -> Result<()> {
+        if *state == State::Rest {
+            self.path.pop();
+        }
+        self.formatter
+            .end_object(&mut self.writer)
+            .map_err(Error::io)
+    }
+
+    /// Serializes the value of a buffered object entry into its buffer.
+    fn serialize_entry_value<T>(
+        &mut self,
+        entry: &mut (PathSegment, Vec<u8>),
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
     {
-        let mut buffer = ryu_js::Buffer::new();
-        let s = buffer.format(value);
-        writer.write_all(s.as_bytes())
+        self.path.push(entry.0.clone());
+        self.writer.buffers.push(Vec::new());
+        let result = value.serialize(&mut *self).map_err(|err| self.locate(err));
+        entry.1 = self.writer.buffers.pop().unwrap_or_default();
+        self.path.pop();
+        result
+    }
+
+    /// Writes the entries of a buffered object in the configured key order
+    /// and closes the object.
+    fn end_ordered(&mut self, mut entries: Vec<(PathSegment, Vec<u8>)>) -> Result<()> {
+        sort_entries(&mut entries, self.key_order);
+        let mut state = State::First;
+        for (segment, value) in &entries {
+            try!(self.serialize_key_str(&state, segment.name()));
+            state = State::Rest;
+            try!(self
+                .formatter
+                .begin_object_value(&mut self.writer)
+                .map_err(Error::io));
+            try!(self.writer.write_all(value).map_err(Error::io));
+            try!(self
+                .formatter
+                .end_object_value(&mut self.writer)
+                .map_err(Error::io));
+        }
+        self.formatter
+            .end_object(&mut self.writer)
+            .map_err(Error::io)
+    }
+}
+
+/// Name of the struct `serde_json::Number` serializes as when serde_json's
+/// `arbitrary_precision` feature is enabled anywhere in the dependency graph.
+const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Name of the struct `serde_json::value::RawValue` serializes as.
+pub(crate) const RAW_VALUE_TOKEN: &str = "$serde_json::private::RawValue";
+
+/// Returns the `f64` closest to the shortest decimal representation of
+/// `value`, so that formatting it as an `f64` prints the same digits.
+fn shortest_f64(value: f32) -> f64 {
+    let mut buffer = ryu_js::Buffer::new();
+    buffer
+        .format_finite(value)
+        .parse()
+        .unwrap_or_else(|_| f64::from(value))
+}
+
+/// One step on the way from the root value to the value being serialized.
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+#[derive(Clone)]
+pub enum PathSegment {
+    Index(usize),
+    Field(&'static str),
+    Key(String),
+}
+
+impl PathSegment {
+    /// Returns the property name of a field or map key segment.
+    fn name(&self) -> &str {
+        match *self {
+            PathSegment::Index(_) => "",
+            PathSegment::Field(name) => name,
+            PathSegment::Key(ref name) => name,
+        }
+    }
+}
+
+/// Sorts buffered object entries according to `order`.
+fn sort_entries(entries: &mut [(PathSegment, Vec<u8>)], order: KeyOrder) {
+    match order {
+        KeyOrder::AsIs => {}
+        KeyOrder::V8 => entries.sort_by(|a, b| {
+            match (array_index(a.0.name()), array_index(b.0.name())) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }),
+        KeyOrder::Sorted => {
+            entries.sort_by(|a, b| a.0.name().encode_utf16().cmp(b.0.name().encode_utf16()));
+        }
+    }
+}
+
+/// Returns the index a property name stands for if JavaScript treats it as
+/// an array index: the canonical decimal form of an integer below `2^32 - 1`.
+pub(crate) fn array_index(name: &str) -> Option<u32> {
+    if name.len() > 1 && name.starts_with('0') || !name.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
     }
+    match name.parse::<u32>() {
+        Ok(index) if index != u32::MAX => Some(index),
+        _ => None,
+    }
+}
+
+/// The destination of the serializer. While the values of an ordered object
+/// are being serialized, output goes to the innermost buffer instead of the
+/// underlying writer.
+struct Output<W> {
+    writer: W,
+    buffers: Vec<Vec<u8>>,
 }
 
+impl<W> io::Write for Output<W>
+where
+    W: io::Write,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.buffers.last_mut() {
+            Some(buffer) => buffer.write(buf),
+            None => self.writer.write(buf),
+        }
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self.buffers.last_mut() {
+            Some(buffer) => buffer.write_all(buf),
+            None => self.writer.write_all(buf),
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        match self.buffers.last_mut() {
+            Some(_) => Ok(()),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+/// Formats a path as a JSONPath expression such as `$.users[3].id`.
+struct DisplayPath<'a>(&'a [PathSegment]);
+
+impl Display for DisplayPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str("$"));
+        for segment in self.0 {
+            try!(match *segment {
+                PathSegment::Index(index) => write!(f, "[{index}]"),
+                PathSegment::Field(name) => write_path_name(f, name),
+                PathSegment::Key(ref name) => write_path_name(f, name),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn write_path_name(f: &mut fmt::Formatter, name: &str) -> fmt::Result {
+    let mut chars = name.chars();
+    let is_identifier = match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == '$' => {
+            chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        }
+        _ => false,
+    };
+    if is_identifier {
+        write!(f, ".{name}")
+    } else {
+        let mut quoted = Vec::with_capacity(name.len() + 2);
+        try!(format_escaped_str(&mut quoted, &mut CompactV8Formatter, name, Escaping::default()).map_err(|_| fmt::Error));
+        write!(f, "[{}]", String::from_utf8_lossy(&quoted))
+    }
+}
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+#[derive(Eq, PartialEq)]
+pub enum State {
+    Empty,
+    First,
+    Rest,
+}
+
+// Not public API. Should be pub(crate).
+#[doc(hidden)]
+pub enum Compound<'a, W: 'a, F: 'a> {
+    Map {
+        ser: &'a mut Serializer<W, F>,
+        state: State,
+    },
+    /// An object whose entries are buffered so that they can be written in a
+    /// different order. Each entry holds its key and its formatted value.
+    Ordered {
+        ser: &'a mut Serializer<W, F>,
+        entries: Vec<(PathSegment, Vec<u8>)>,
+    },
+    /// Pre-formatted JSON text coming from serde_json's private number and
+    /// raw value representations, written out as is.
+    Verbatim { ser: &'a mut Serializer<W, F> },
+}
+
+impl<W, F> ser::SerializeSeq for Compound<'_, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match *self {
+            Compound::Map {
+                ref mut ser,
+                ref mut state,
+            } => {
+                try!(ser
+                    .formatter
+                    .begin_array_value(&mut ser.writer, *state == State::First)
+                    .map_err(Error::io));
+                if *state == State::First {
+                    ser.path.push(PathSegment::Index(0));
+                } else if let Some(&mut PathSegment::Index(ref mut index)) = ser.path.last_mut() {
+                    *index += 1;
+                }
+                *state = State::Rest;
+                try!(value.serialize(&mut **ser).map_err(|err| ser.locate(err)));
+                ser.formatter
+                    .end_array_value(&mut ser.writer)
+                    .map_err(Error::io)
+            }
+            Compound::Ordered { .. } | Compound::Verbatim { .. } => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        match self {
+            Compound::Map { ser, state } => match state {
+                State::Empty => Ok(()),
+                _ => ser.end_array(&state),
+            },
+            Compound::Ordered { .. } | Compound::Verbatim { .. } => unreachable!(),
+        }
+    }
+}
+
+impl<W, F> ser::SerializeTuple for Compound<'_, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<W, F> ser::SerializeTupleStruct for Compound<'_, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<W, F> ser::SerializeTupleVariant for Compound<'_, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        match self {
+            Compound::Map { ser, state } => {
+                match state {
+                    State::Empty => {}
+                    _ => try!(ser.end_array(&state)),
+                }
+                ser.end_variant()
+            }
+            Compound::Ordered { .. } | Compound::Verbatim { .. } => unreachable!(),
+        }
+    }
+}
+
+impl<W, F> ser::SerializeMap for Compound<'_, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match *self {
+            Compound::Map {
+                ref mut ser,
+                ref mut state,
+            } => {
+                let key = try!(key
+                    .serialize(ser.map_key_serializer())
+                    .map_err(|err| ser.locate_key(state, err)));
+                try!(ser.serialize_key_str(state, &key));
+                ser.set_key_segment(state, PathSegment::Key(key));
+                *state = State::Rest;
+                Ok(())
+            }
+            Compound::Ordered {
+                ref mut ser,
+                ref mut entries,
+            } => {
+                let key = try!(key
+                    .serialize(ser.map_key_serializer())
+                    .map_err(|err| ser.locate(err)));
+                entries.push((PathSegment::Key(key), Vec::new()));
+                Ok(())
+            }
+            Compound::Verbatim { .. } => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match *self {
+            Compound::Map { ref mut ser, .. } => {
+                try!(ser
+                    .formatter
+                    .begin_object_value(&mut ser.writer)
+                    .map_err(Error::io));
+                try!(value.serialize(&mut **ser).map_err(|err| ser.locate(err)));
+                ser.formatter
+                    .end_object_value(&mut ser.writer)
+                    .map_err(Error::io)
+            }
+            Compound::Ordered {
+                ref mut ser,
+                ref mut entries,
+            } => match entries.last_mut() {
+                Some(entry) => ser.serialize_entry_value(entry, value),
+                None => Err(ser::Error::custom(
+                    "serialize_value called before serialize_key",
+                )),
+            },
+            Compound::Verbatim { .. } => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        match self {
+            Compound::Map { ser, state } => match state {
+                State::Empty => Ok(()),
+                _ => ser.end_object(&state),
+            },
+            Compound::Ordered { ser, entries } => ser.end_ordered(entries),
+            Compound::Verbatim { .. } => unreachable!(),
+        }
+    }
+}
+
+impl<W, F> ser::SerializeStruct for Compound<'_, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match *self {
+            Compound::Map {
+                ref mut ser,
+                ref mut state,
+            } => {
+                try!(ser.serialize_key_str(state, key));
+                ser.set_key_segment(state, PathSegment::Field(key));
+                *state = State::Rest;
+                ser::SerializeMap::serialize_value(self, value)
+            }
+            Compound::Ordered {
+                ref mut ser,
+                ref mut entries,
+            } => {
+                let mut entry = (PathSegment::Field(key), Vec::new());
+                try!(ser.serialize_entry_value(&mut entry, value));
+                entries.push(entry);
+                Ok(())
+            }
+            Compound::Verbatim { ref mut ser } => {
+                if key == NUMBER_TOKEN || key == RAW_VALUE_TOKEN {
+                    value.serialize(VerbatimEmitter(&mut **ser))
+                } else {
+                    Err(ser::Error::custom("unexpected field in private serde_json struct"))
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        match self {
+            Compound::Map { .. } | Compound::Ordered { .. } => ser::SerializeMap::end(self),
+            Compound::Verbatim { .. } => Ok(()),
+        }
+    }
+}
+
+impl<W, F> ser::SerializeStructVariant for Compound<'_, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match *self {
+            Compound::Map { .. } | Compound::Ordered { .. } => {
+                ser::SerializeStruct::serialize_field(self, key, value)
+            }
+            Compound::Verbatim { .. } => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        match self {
+            Compound::Map { ser, state } => {
+                match state {
+                    State::Empty => {}
+                    _ => try!(ser.end_object(&state)),
+                }
+                ser.end_variant()
+            }
+            Compound::Ordered { ser, entries } => {
+                try!(ser.end_ordered(entries));
+                ser.end_variant()
+            }
+            Compound::Verbatim { .. } => unreachable!(),
+        }
+    }
+}
+
+fn key_must_be_a_string() -> Error {
+    Error::new(ErrorKind::KeyMustBeAString, "key must be a string")
+}
+
+fn expected_verbatim_str() -> Error {
+    ser::Error::custom("expected a string in private serde_json struct")
+}
+
+/// Turns a map key into the property name it is written as, the way
+/// `String(key)` coerces it.
+#[derive(Clone, Copy)]
+struct MapKeySerializer {
+    policy: KeyPolicy,
+    f32_format: F32Format,
+    /// Whether this is an element of a sequence key, where `null` is written
+    /// as nothing like `Array.prototype.join` does.
+    element: bool,
+}
+
+impl MapKeySerializer {
+    fn null(self) -> String {
+        if self.element { "" } else { "null" }.to_owned()
+    }
+
+    fn elements(self) -> Result<KeyElements> {
+        match self.policy {
+            KeyPolicy::Coerce | KeyPolicy::CoerceObjects => Ok(KeyElements {
+                key: MapKeySerializer {
+                    element: true,
+                    ..self
+                },
+                elements: Vec::new(),
+            }),
+            KeyPolicy::Strict => Err(key_must_be_a_string()),
+        }
+    }
+
+    fn object(self) -> Result<KeyObject> {
+        match self.policy {
+            KeyPolicy::CoerceObjects => Ok(KeyObject),
+            KeyPolicy::Coerce | KeyPolicy::Strict => Err(key_must_be_a_string()),
+        }
+    }
+}
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = Error;
+
+    type SerializeSeq = KeyElements;
+    type SerializeTuple = KeyElements;
+    type SerializeTupleStruct = KeyElements;
+    type SerializeTupleVariant = KeyObject;
+    type SerializeMap = KeyObject;
+    type SerializeStruct = KeyObject;
+    type SerializeStructVariant = KeyObject;
+
+    #[inline]
+    fn serialize_str(self, value: &str) -> Result<String> {
+        Ok(value.to_owned())
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_owned())
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == JS_STRING_TOKEN {
+            // Property names with lone surrogates cannot be held by a `String`.
+            return Err(key_must_be_a_string());
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, value: bool) -> Result<String> {
+        Ok(if value { "true" } else { "false" }.to_owned())
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<String> {
+        Ok(itoa::Buffer::new().format(value).to_owned())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<String> {
+        Ok(itoa::Buffer::new().format(value).to_owned())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<String> {
+        Ok(itoa::Buffer::new().format(value).to_owned())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<String> {
+        Ok(itoa::Buffer::new().format(value).to_owned())
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<String> {
+        Ok(itoa::Buffer::new().format(value).to_owned())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<String> {
+        Ok(itoa::Buffer::new().format(value).to_owned())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<String> {
+        Ok(itoa::Buffer::new().format(value).to_owned())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<String> {
+        Ok(itoa::Buffer::new().format(value).to_owned())
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<String> {
+        match self.f32_format {
+            F32Format::Widened => self.serialize_f64(f64::from(value)),
+            F32Format::Shortest => self.serialize_f64(shortest_f64(value)),
+        }
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<String> {
+        // `String(-0)` is `"0"`.
+        let value = if value == 0.0 { 0.0 } else { value };
+        Ok(ryu_js::Buffer::new().format(value).to_owned())
+    }
+
+    fn serialize_char(self, value: char) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<String> {
+        let mut elements = try!(self.elements());
+        for byte in value {
+            try!(ser::SerializeSeq::serialize_element(&mut elements, byte));
+        }
+        ser::SerializeSeq::end(elements)
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Ok(self.null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Ok(self.null())
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        try!(self.object());
+        Ok(OBJECT_KEY.to_owned())
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Ok(self.null())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.elements()
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        self.elements()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.elements()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.object()
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.object()
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        if name == NUMBER_TOKEN || name == RAW_VALUE_TOKEN {
+            return Err(key_must_be_a_string());
+        }
+        self.object()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.object()
+    }
+
+    fn collect_str<T>(self, value: &T) -> Result<String>
+    where
+        T: ?Sized + Display,
+    {
+        Ok(value.to_string())
+    }
+}
+
+/// What `String(object)` gives for a plain object.
+const OBJECT_KEY: &str = "[object Object]";
+
+/// Coerces a sequence key into its elements joined with commas, the way
+/// `Array.prototype.toString` does.
+struct KeyElements {
+    key: MapKeySerializer,
+    elements: Vec<String>,
+}
+
+impl ser::SerializeSeq for KeyElements {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let element = try!(value.serialize(self.key));
+        self.elements.push(element);
+        Ok(())
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.elements.join(","))
+    }
+}
+
+impl ser::SerializeTuple for KeyElements {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<String> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for KeyElements {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<String> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Coerces a map, struct or enum variant with data key into
+/// `"[object Object]"`, ignoring its contents.
+struct KeyObject;
+
+impl ser::SerializeTupleVariant for KeyObject {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(OBJECT_KEY.to_owned())
+    }
+}
+
+impl ser::SerializeMap for KeyObject {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, _key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, _value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(OBJECT_KEY.to_owned())
+    }
+}
+
+impl ser::SerializeStruct for KeyObject {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(OBJECT_KEY.to_owned())
+    }
+}
+
+impl ser::SerializeStructVariant for KeyObject {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(OBJECT_KEY.to_owned())
+    }
+}
+
+/// Writes the string payload of serde_json's private number and raw value
+/// structs without quoting or escaping it, and the byte payload of a
+/// `JsString` with lone surrogates as an escaped string.
+struct VerbatimEmitter<'a, W: 'a, F: 'a>(&'a mut Serializer<W, F>);
+
+impl<W, F> ser::Serializer for VerbatimEmitter<'_, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_str(self, value: &str) -> Result<()> {
+        let VerbatimEmitter(ser) = self;
+        if value.chars().any(|c| ser.escaping.escapes(c)) {
+            let mut escaped = String::with_capacity(value.len());
+            for c in value.chars() {
+                if ser.escaping.escapes(c) {
+                    push_utf16_escapes(&mut escaped, c);
+                } else {
+                    escaped.push(c);
+                }
+            }
+            return ser
+                .formatter
+                .write_raw_fragment(&mut ser.writer, &escaped)
+                .map_err(Error::io);
+        }
+        ser.formatter
+            .write_raw_fragment(&mut ser.writer, value)
+            .map_err(Error::io)
+    }
+
+    fn serialize_bool(self, _value: bool) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_i8(self, _value: i8) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_i16(self, _value: i16) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_i32(self, _value: i32) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_i64(self, _value: i64) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_u8(self, _value: u8) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_u16(self, _value: u16) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_u32(self, _value: u32) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_u64(self, _value: u64) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_f32(self, _value: f32) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_f64(self, _value: f64) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_char(self, _value: char) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    // Asks for the code units of a `JsString` as bytes.
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        let VerbatimEmitter(ser) = self;
+        match utf16_from_bytes(value) {
+            Some(units) => {
+                format_escaped_utf16(&mut ser.writer, &mut ser.formatter, &units, ser.escaping)
+                    .map_err(Error::io)
+            }
+            None => Err(ser::Error::custom("invalid UTF-16 in private JsString struct")),
+        }
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(expected_verbatim_str())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(expected_verbatim_str())
+    }
+}
+
+/// This structure compacts a JSON value with no extra whitespace.
+#[derive(Clone, Debug)]
+pub struct CompactV8Formatter;
+
+impl Formatter for CompactV8Formatter {
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        write_v8_f64(writer, f64::from(value))
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        write_v8_f64(writer, value)
+    }
+}
+
+/// This structure writes JSON in the canonical form of RFC 8785: no
+/// whitespace, and every number written as JavaScript writes the nearest
+/// `f64`.
+///
+/// Use it through `Serializer::canonical`, which also sorts keys and rejects
+/// non-finite numbers as the RFC requires.
+#[derive(Clone, Debug)]
+pub struct CanonicalFormatter;
+
+impl Formatter for CanonicalFormatter {
+    #[inline]
+    fn write_i64<W>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        write_v8_f64(writer, value as f64)
+    }
+
+    #[inline]
+    fn write_u64<W>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        write_v8_f64(writer, value as f64)
+    }
+
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        write_v8_f64(writer, f64::from(value))
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        write_v8_f64(writer, value)
+    }
+
+    #[inline]
+    fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        match value.parse::<f64>() {
+            Ok(value) if value.is_finite() => write_v8_f64(writer, value),
+            _ => writer.write_all(value.as_bytes()),
+        }
+    }
+
+    /// Parses the fragment and writes it again in canonical form, so that
+    /// `RawJson` and `RawValue` cannot bring in other spellings of a value.
+    fn write_raw_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        let mut read = Reader::new(fragment);
+        try!(self.write_fragment_value(writer, &mut read));
+        read.end().map_err(fragment_error)
+    }
+}
+
+impl CanonicalFormatter {
+    /// Writes the value at `read` in canonical form. Strings are read as
+    /// `JSON.parse` reads them, keeping the lone surrogates that `RawJson`
+    /// accepts, and numbers are read here rather than through `Value`, whose
+    /// numbers may come back as raw fragments.
+    fn write_fragment_value<W>(&mut self, writer: &mut W, read: &mut Reader) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        match read.peek_token() {
+            Some(b'"') => match try!(read.parse_str().map_err(fragment_error)) {
+                Str::Borrowed(s) => format_escaped_str(writer, self, s, Escaping::default()),
+                Str::Owned(s) => format_escaped_str(writer, self, &s, Escaping::default()),
+                Str::Utf16(units) => format_escaped_utf16(writer, self, &units, Escaping::default()),
+            },
+            Some(b'-' | b'0'..=b'9') => {
+                let start = read.index();
+                try!(read.scan_number().map_err(fragment_error));
+                let text = read.slice(start);
+                match text.parse::<f64>() {
+                    Ok(value) if value.is_finite() => write_v8_f64(writer, value),
+                    _ => Err(io::Error::from(Error::new(
+                        ErrorKind::NonFiniteNumber,
+                        format_args!("{text} is not a finite number"),
+                    ))),
+                }
+            }
+            Some(b'[') => {
+                read.next();
+                try!(self.begin_array(writer));
+                let mut first = true;
+                while try!(fragment_has_next(read, b']', first)) {
+                    try!(self.begin_array_value(writer, first));
+                    try!(self.write_fragment_value(writer, read));
+                    try!(self.end_array_value(writer));
+                    first = false;
+                }
+                self.end_array(writer)
+            }
+            Some(b'{') => {
+                read.next();
+                // Each property is written on its own so that they can be
+                // sorted by the UTF-16 code units of their names.
+                let mut properties: Vec<(Vec<u16>, Vec<u8>)> = Vec::new();
+                let mut positions: HashMap<Vec<u16>, usize> = HashMap::new();
+                let mut first = true;
+                while try!(fragment_has_next(read, b'}', first)) {
+                    first = false;
+                    if read.peek_token() != Some(b'"') {
+                        return Err(fragment_error(read.error("key must be a string")));
+                    }
+                    let name: Vec<u16> = match try!(read.parse_str().map_err(fragment_error)) {
+                        Str::Borrowed(s) => s.encode_utf16().collect(),
+                        Str::Owned(s) => s.encode_utf16().collect(),
+                        Str::Utf16(units) => units,
+                    };
+                    if read.peek_token() != Some(b':') {
+                        return Err(fragment_error(read.error("expected `:`")));
+                    }
+                    read.next();
+                    let mut property = Vec::new();
+                    try!(format_escaped_utf16(&mut property, self, &name, Escaping::default()));
+                    try!(self.end_object_key(&mut property));
+                    try!(self.begin_object_value(&mut property));
+                    try!(self.write_fragment_value(&mut property, read));
+                    try!(self.end_object_value(&mut property));
+                    // A repeated name takes the last value, as with `JSON.parse`.
+                    match positions.get(&name) {
+                        Some(&index) => properties[index].1 = property,
+                        None => {
+                            positions.insert(name.clone(), properties.len());
+                            properties.push((name, property));
+                        }
+                    }
+                }
+                properties.sort_by(|a, b| a.0.cmp(&b.0));
+                try!(self.begin_object(writer));
+                for (index, property) in properties.iter().enumerate() {
+                    try!(self.begin_object_key(writer, index == 0));
+                    try!(writer.write_all(&property.1));
+                }
+                self.end_object(writer)
+            }
+            Some(b't') => write_fragment_literal(writer, read, "true"),
+            Some(b'f') => write_fragment_literal(writer, read, "false"),
+            Some(b'n') => write_fragment_literal(writer, read, "null"),
+            _ => Err(fragment_error(read.error("expected value"))),
+        }
+    }
+}
+
+/// Consumes the comma before the next element or property of a raw
+/// fragment, or the bracket closing it, and tells which one it was.
+fn fragment_has_next(read: &mut Reader, close: u8, first: bool) -> io::Result<bool> {
+    match read.peek_token() {
+        Some(byte) if byte == close => {
+            read.next();
+            Ok(false)
+        }
+        Some(b',') if !first => {
+            read.next();
+            Ok(true)
+        }
+        _ if first => Ok(true),
+        _ => Err(fragment_error(read.error("expected `,` or a closing bracket"))),
+    }
+}
+
+fn write_fragment_literal<W>(writer: &mut W, read: &mut Reader, literal: &str) -> io::Result<()>
+where
+    W: ?Sized + io::Write,
+{
+    try!(read.parse_ident(literal).map_err(fragment_error));
+    writer.write_all(literal.as_bytes())
+}
+
+fn fragment_error(error: serde_json::Error) -> io::Error {
+    io::Error::from(Error::from(error))
+}
+
+/// This structure pretty prints a JSON value to make it human readable.
+#[derive(Clone, Debug)]
+pub struct PrettyV8Formatter<'a> {
+    inner: V8Numbers<GapFormatter<'a>>,
+}
+
+impl<'a> PrettyV8Formatter<'a> {
+    /// Construct a pretty printer formatter that defaults to using two spaces for indentation.
+    #[must_use]
+    pub fn new() -> Self {
+        PrettyV8Formatter {
+            inner: V8Numbers(GapFormatter::new()),
+        }
+    }
+
+    /// Construct a pretty printer formatter that uses the `indent` string for indentation.
+    #[must_use]
+    pub fn with_indent(indent: &'a [u8]) -> Self {
+        PrettyV8Formatter {
+            inner: V8Numbers(GapFormatter::with_indent(indent)),
+        }
+    }
+
+    /// Construct a pretty printer formatter indenting like
+    /// `JSON.stringify(value, null, space)`.
+    ///
+    /// ```edition2018
+    /// use serde::Serialize;
+    /// use serde_json_v8::ser::{PrettyV8Formatter, Space};
+    ///
+    /// let formatter = PrettyV8Formatter::with_space(Space::from(0));
+    /// let mut ser = serde_json_v8::Serializer::with_formatter(Vec::new(), formatter);
+    /// vec![1, 2].serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), b"[1,2]");
+    /// ```
+    #[must_use]
+    pub fn with_space(space: Space<'a>) -> Self {
+        PrettyV8Formatter {
+            inner: V8Numbers(GapFormatter::with_space(space)),
+        }
+    }
+}
+
+impl Default for PrettyV8Formatter<'_> {
+    fn default() -> Self {
+        PrettyV8Formatter::new()
+    }
+}
+
+impl Formatter for PrettyV8Formatter<'_> {
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_f32(writer, value)
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_f64(writer, value)
+    }
+
+    #[inline]
+    fn write_null<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_null(writer)
+    }
+
+    #[inline]
+    fn write_bool<W>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_bool(writer, value)
+    }
+
+    #[inline]
+    fn write_i8<W>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_i8(writer, value)
+    }
+
+    #[inline]
+    fn write_i16<W>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_i16(writer, value)
+    }
+
+    #[inline]
+    fn write_i32<W>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_i32(writer, value)
+    }
+
+    #[inline]
+    fn write_i64<W>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_i64(writer, value)
+    }
+
+    #[inline]
+    fn write_u8<W>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_u8(writer, value)
+    }
+
+    #[inline]
+    fn write_u16<W>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_u16(writer, value)
+    }
+
+    #[inline]
+    fn write_u32<W>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_u32(writer, value)
+    }
+
+    #[inline]
+    fn write_u64<W>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_u64(writer, value)
+    }
+
+    #[inline]
+    fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_number_str(writer, value)
+    }
+
+    #[inline]
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.begin_string(writer)
+    }
+
+    #[inline]
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.end_string(writer)
+    }
+
+    #[inline]
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_string_fragment(writer, fragment)
+    }
+
+    #[inline]
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_char_escape(writer, char_escape)
+    }
+
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.begin_array(writer)
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.end_array(writer)
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.begin_array_value(writer, first)
+    }
+
+    #[inline]
+    fn end_array_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.end_array_value(writer)
+    }
+
+    #[inline]
+    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.begin_object(writer)
+    }
+
+    #[inline]
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.end_object(writer)
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.begin_object_key(writer, first)
+    }
+
+    #[inline]
+    fn end_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.end_object_key(writer)
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.begin_object_value(writer)
+    }
+
+    #[inline]
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.end_object_value(writer)
+    }
+
+    #[inline]
+    fn write_raw_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.inner.write_raw_fragment(writer, fragment)
+    }
+}
+
+/// The `space` argument of `JSON.stringify`, controlling indentation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Space<'a> {
+    /// Indent with this many spaces. The number is truncated to an integer
+    /// and clamped to 10; values below 1 produce compact output.
+    Number(f64),
+    /// Indent with the first 10 UTF-16 code units of this string; an empty
+    /// string produces compact output. A surrogate pair straddling the limit
+    /// is dropped, as Rust strings cannot hold half of it.
+    String(&'a str),
+}
+
+impl<'a> Space<'a> {
+    /// Returns the indentation unit, or "gap" in the words of the
+    /// specification, that this space stands for.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn gap(&self) -> &'a [u8] {
+        const SPACES: &[u8] = b"          ";
+        match *self {
+            Space::Number(n) => {
+                // NaN and anything below 1 fail the comparison and mean no
+                // indentation; the rest is truncated like ToIntegerOrInfinity.
+                let n = if n >= 1.0 { n.min(10.0) as usize } else { 0 };
+                &SPACES[..n]
+            }
+            Space::String(s) => {
+                let mut units = 0;
+                let mut end = 0;
+                for c in s.chars() {
+                    units += c.len_utf16();
+                    if units > 10 {
+                        break;
+                    }
+                    end += c.len_utf8();
+                }
+                &s.as_bytes()[..end]
+            }
+        }
+    }
+}
+
+impl From<i32> for Space<'_> {
+    fn from(n: i32) -> Self {
+        Space::Number(f64::from(n))
+    }
+}
+
+impl From<f64> for Space<'_> {
+    fn from(n: f64) -> Self {
+        Space::Number(n)
+    }
+}
+
+impl<'a> From<&'a str> for Space<'a> {
+    fn from(s: &'a str) -> Self {
+        Space::String(s)
+    }
+}
+
+/// This structure indents a JSON value with a "gap" string the way
+/// `JSON.stringify` does. When it is constructed from a `Space` that stands
+/// for an empty gap, unlike serde_json's `PrettyFormatter`, it produces
+/// compact output.
+#[derive(Clone, Debug)]
+pub struct GapFormatter<'a> {
+    current_indent: usize,
+    has_value: bool,
+    /// `None` when the output is compact.
+    gap: Option<&'a [u8]>,
+}
+
+impl<'a> GapFormatter<'a> {
+    /// Construct a formatter that defaults to using two spaces for indentation.
+    #[must_use]
+    pub fn new() -> Self {
+        GapFormatter::with_indent(b"  ")
+    }
+
+    /// Construct a formatter that uses the `indent` string for indentation.
+    /// Like serde_json's `PrettyFormatter`, an empty `indent` still puts
+    /// every value on a line of its own.
+    #[must_use]
+    pub fn with_indent(indent: &'a [u8]) -> Self {
+        GapFormatter {
+            current_indent: 0,
+            has_value: false,
+            gap: Some(indent),
+        }
+    }
+
+    /// Construct a formatter indenting like
+    /// `JSON.stringify(value, null, space)`.
+    #[must_use]
+    pub fn with_space(space: Space<'a>) -> Self {
+        let gap = space.gap();
+        GapFormatter {
+            current_indent: 0,
+            has_value: false,
+            gap: if gap.is_empty() { None } else { Some(gap) },
+        }
+    }
+
+    fn write_newline<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if let Some(gap) = self.gap {
+            try!(writer.write_all(b"\n"));
+            for _ in 0..self.current_indent {
+                try!(writer.write_all(gap));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for GapFormatter<'_> {
+    fn default() -> Self {
+        GapFormatter::new()
+    }
+}
+
+impl Formatter for GapFormatter<'_> {
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent += 1;
+        self.has_value = false;
+        writer.write_all(b"[")
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent -= 1;
+
+        if self.has_value {
+            try!(self.write_newline(writer));
+        }
+
+        writer.write_all(b"]")
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if !first {
+            try!(writer.write_all(b","));
+        }
+        self.write_newline(writer)
+    }
+
+    #[inline]
+    fn end_array_value<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.has_value = true;
+        Ok(())
+    }
+
+    #[inline]
+    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent += 1;
+        self.has_value = false;
+        writer.write_all(b"{")
+    }
+
+    #[inline]
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent -= 1;
+
+        if self.has_value {
+            try!(self.write_newline(writer));
+        }
+
+        writer.write_all(b"}")
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if !first {
+            try!(writer.write_all(b","));
+        }
+        self.write_newline(writer)
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if self.gap.is_none() {
+            writer.write_all(b":")
+        } else {
+            writer.write_all(b": ")
+        }
+    }
+
+    #[inline]
+    fn end_object_value<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.has_value = true;
+        Ok(())
+    }
+}
+
+/// This structure writes a value as a JavaScript expression that is easier to
+/// read than JSON: keys that are ASCII identifiers are not quoted, and
+/// optionally strings use single quotes and indented containers end with a
+/// trailing comma. Numbers are written the way `Number.prototype.toString`
+/// prints them.
+///
+/// Use it through `Serializer::js_literal`, which also makes number literals
+/// reach the formatter when they are enabled.
+///
+/// ```edition2018
+/// use serde::Serialize;
+/// use serde_json_v8::ser::JsLiteralFormatter;
+///
+/// let mut formatter = JsLiteralFormatter::new();
+/// formatter.set_single_quotes(true);
+/// formatter.set_trailing_commas(true);
+/// formatter.set_number_literals(true);
+/// let mut ser = serde_json_v8::Serializer::js_literal(Vec::new(), formatter);
+/// let value = (serde_json::json!({"max-age": 1e21, "name": "it's"}), u64::MAX, -0.0);
+/// value.serialize(&mut ser).unwrap();
+/// assert_eq!(
+///     String::from_utf8(ser.into_inner()).unwrap(),
+///     "[\n  {\n    'max-age': 1e+21,\n    name: 'it\\'s',\n  },\n  18446744073709551615n,\n  -0,\n]"
+/// );
+/// ```
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug)]
+pub struct JsLiteralFormatter<'a> {
+    current_indent: usize,
+    has_value: bool,
+    gap: &'a [u8],
+    trailing_commas: bool,
+    single_quotes: bool,
+    number_literals: bool,
+    in_key: bool,
+    /// The contents of the key being written, held back until it is known
+    /// whether it needs quotes.
+    key: Option<Vec<u8>>,
+}
+
+impl<'a> JsLiteralFormatter<'a> {
+    /// Construct a formatter that defaults to using two spaces for indentation.
+    #[must_use]
+    pub fn new() -> Self {
+        JsLiteralFormatter::with_indent(b"  ")
+    }
+
+    /// Construct a formatter that uses the `indent` string for indentation.
+    /// An empty string produces compact output.
+    #[must_use]
+    pub fn with_indent(indent: &'a [u8]) -> Self {
+        JsLiteralFormatter {
+            current_indent: 0,
+            has_value: false,
+            gap: indent,
+            trailing_commas: false,
+            single_quotes: false,
+            number_literals: false,
+            in_key: false,
+            key: None,
+        }
+    }
+
+    /// Construct a formatter indenting like
+    /// `JSON.stringify(value, null, space)`.
+    #[must_use]
+    pub fn with_space(space: Space<'a>) -> Self {
+        JsLiteralFormatter::with_indent(space.gap())
+    }
+
+    /// Sets whether the last element of an indented array or object is
+    /// followed by a comma.
+    pub fn set_trailing_commas(&mut self, trailing_commas: bool) {
+        self.trailing_commas = trailing_commas;
+    }
+
+    /// Sets whether strings and quoted keys are delimited by single quotes
+    /// instead of double quotes.
+    pub fn set_single_quotes(&mut self, single_quotes: bool) {
+        self.single_quotes = single_quotes;
+    }
+
+    /// Sets whether `NaN`, `Infinity`, `-Infinity` and `-0` are written as
+    /// such, and integers outside of the safe integer range as `BigInt`
+    /// literals like `18446744073709551615n`. Without this option numbers are
+    /// written as `JSON.stringify` would.
+    ///
+    /// This takes effect through `Serializer::js_literal`, which picks the
+    /// number and integer policies writing these literals.
+    pub fn set_number_literals(&mut self, number_literals: bool) {
+        self.number_literals = number_literals;
+    }
+
+    fn write_newline<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if self.gap.is_empty() {
+            return Ok(());
+        }
+        try!(writer.write_all(b"\n"));
+        for _ in 0..self.current_indent {
+            try!(writer.write_all(self.gap));
+        }
+        Ok(())
+    }
+
+    fn write_end<W>(&mut self, writer: &mut W, end: &[u8]) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent -= 1;
+
+        if self.has_value && !self.gap.is_empty() {
+            if self.trailing_commas {
+                try!(writer.write_all(b","));
+            }
+            try!(self.write_newline(writer));
+        }
+
+        writer.write_all(end)
+    }
+
+    fn quote(&self) -> &'static [u8] {
+        if self.single_quotes {
+            b"'"
+        } else {
+            b"\""
+        }
+    }
+}
+
+impl Default for JsLiteralFormatter<'_> {
+    fn default() -> Self {
+        JsLiteralFormatter::new()
+    }
+}
+
+/// Returns whether a property key can be written without quotes. Only ASCII
+/// identifiers are considered; reserved words are allowed as property names.
+fn is_identifier_key(key: &[u8]) -> bool {
+    match key.split_first() {
+        Some((&first, rest)) => {
+            (first.is_ascii_alphabetic() || first == b'_' || first == b'$')
+                && rest
+                    .iter()
+                    .all(|&b| b.is_ascii_alphanumeric() || b == b'_' || b == b'$')
+        }
+        None => false,
+    }
+}
+
+fn write_js_string_fragment<W>(writer: &mut W, fragment: &str, single_quotes: bool) -> io::Result<()>
+where
+    W: ?Sized + io::Write,
+{
+    if !single_quotes {
+        return writer.write_all(fragment.as_bytes());
+    }
+    let mut pieces = fragment.split('\'');
+    if let Some(first) = pieces.next() {
+        try!(writer.write_all(first.as_bytes()));
+    }
+    for piece in pieces {
+        try!(writer.write_all(b"\\'"));
+        try!(writer.write_all(piece.as_bytes()));
+    }
+    Ok(())
+}
+
+fn write_js_char_escape<W>(writer: &mut W, char_escape: &CharEscape, single_quotes: bool) -> io::Result<()>
+where
+    W: ?Sized + io::Write,
+{
+    let s: &[u8] = match *char_escape {
+        CharEscape::Quote if single_quotes => b"\"",
+        CharEscape::Quote => b"\\\"",
+        CharEscape::ReverseSolidus => b"\\\\",
+        CharEscape::Solidus => b"\\/",
+        CharEscape::Backspace => b"\\b",
+        CharEscape::FormFeed => b"\\f",
+        CharEscape::LineFeed => b"\\n",
+        CharEscape::CarriageReturn => b"\\r",
+        CharEscape::Tab => b"\\t",
+        CharEscape::AsciiControl(byte) => {
+            return write!(writer, "\\u{byte:04x}");
+        }
+    };
+    writer.write_all(s)
+}
+
+impl Formatter for JsLiteralFormatter<'_> {
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        write_v8_f64(writer, f64::from(value))
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        write_v8_f64(writer, value)
+    }
+
+    #[inline]
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if self.in_key {
+            self.key = Some(Vec::new());
+            return Ok(());
+        }
+        writer.write_all(self.quote())
+    }
+
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        match self.key.take() {
+            None => writer.write_all(self.quote()),
+            Some(ref key) if key == b"__proto__" => {
+                // A literal `__proto__` property sets the prototype of the
+                // object instead of creating a property; a computed key does
+                // not.
+                try!(writer.write_all(b"["));
+                try!(writer.write_all(self.quote()));
+                try!(writer.write_all(key));
+                try!(writer.write_all(self.quote()));
+                writer.write_all(b"]")
+            }
+            Some(ref key) if is_identifier_key(key) => writer.write_all(key),
+            Some(key) => {
+                try!(writer.write_all(self.quote()));
+                try!(writer.write_all(&key));
+                writer.write_all(self.quote())
+            }
+        }
+    }
+
+    #[inline]
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        match self.key {
+            Some(ref mut key) => write_js_string_fragment(key, fragment, self.single_quotes),
+            None => write_js_string_fragment(writer, fragment, self.single_quotes),
+        }
+    }
+
+    #[inline]
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        match self.key {
+            Some(ref mut key) => write_js_char_escape(key, &char_escape, self.single_quotes),
+            None => write_js_char_escape(writer, &char_escape, self.single_quotes),
+        }
+    }
+
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent += 1;
+        self.has_value = false;
+        writer.write_all(b"[")
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.write_end(writer, b"]")
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if !first {
+            try!(writer.write_all(b","));
+        }
+        self.write_newline(writer)
+    }
+
+    #[inline]
+    fn end_array_value<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.has_value = true;
+        Ok(())
+    }
+
+    #[inline]
+    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent += 1;
+        self.has_value = false;
+        writer.write_all(b"{")
+    }
+
+    #[inline]
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.write_end(writer, b"}")
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.in_key = true;
+        if !first {
+            try!(writer.write_all(b","));
+        }
+        self.write_newline(writer)
+    }
+
+    #[inline]
+    fn end_object_key<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.in_key = false;
+        Ok(())
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if self.gap.is_empty() {
+            writer.write_all(b":")
+        } else {
+            writer.write_all(b": ")
+        }
+    }
+
+    #[inline]
+    fn end_object_value<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.has_value = true;
+        Ok(())
+    }
+}
+
+/// Adds V8 number formatting to any formatter.
+///
+/// Floating point numbers are written the way `Number.prototype.toString`
+/// prints them, every other method is forwarded to the wrapped formatter.
+///
+/// ```edition2018
+/// use serde::Serialize;
+/// use serde_json::ser::CompactFormatter;
+/// use serde_json_v8::ser::V8Numbers;
+///
+/// let mut ser = serde_json_v8::Serializer::with_formatter(Vec::new(), V8Numbers(CompactFormatter));
+/// vec![1.0, 1e21].serialize(&mut ser).unwrap();
+/// assert_eq!(ser.into_inner(), b"[1,1e+21]");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct V8Numbers<F>(pub F);
+
+impl<F> Formatter for V8Numbers<F>
+where
+    F: Formatter,
+{
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        write_v8_f64(writer, f64::from(value))
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        write_v8_f64(writer, value)
+    }
+
+    #[inline]
+    fn write_null<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_null(writer)
+    }
+
+    #[inline]
+    fn write_bool<W>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_bool(writer, value)
+    }
+
+    #[inline]
+    fn write_i8<W>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_i8(writer, value)
+    }
+
+    #[inline]
+    fn write_i16<W>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_i16(writer, value)
+    }
+
+    #[inline]
+    fn write_i32<W>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_i32(writer, value)
+    }
+
+    #[inline]
+    fn write_i64<W>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_i64(writer, value)
+    }
+
+    #[inline]
+    fn write_u8<W>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_u8(writer, value)
+    }
+
+    #[inline]
+    fn write_u16<W>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_u16(writer, value)
+    }
+
+    #[inline]
+    fn write_u32<W>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_u32(writer, value)
+    }
+
+    #[inline]
+    fn write_u64<W>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_u64(writer, value)
+    }
+
+    #[inline]
+    fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_number_str(writer, value)
+    }
+
+    #[inline]
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_string(writer)
+    }
+
+    #[inline]
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.end_string(writer)
+    }
+
+    #[inline]
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_string_fragment(writer, fragment)
+    }
+
+    #[inline]
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_char_escape(writer, char_escape)
+    }
+
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_array(writer)
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.end_array(writer)
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_array_value(writer, first)
+    }
+
+    #[inline]
+    fn end_array_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.end_array_value(writer)
+    }
+
+    #[inline]
+    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_object(writer)
+    }
+
+    #[inline]
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.end_object(writer)
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_object_key(writer, first)
+    }
+
+    #[inline]
+    fn end_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.end_object_key(writer)
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.begin_object_value(writer)
+    }
+
+    #[inline]
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.end_object_value(writer)
+    }
+
+    #[inline]
+    fn write_raw_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.0.write_raw_fragment(writer, fragment)
+    }
+}
+
+/// Writes a float the way `Number.prototype.toString` prints it.
+#[inline]
+fn write_v8_f64<W>(writer: &mut W, value: f64) -> io::Result<()>
+where
+    W: ?Sized + io::Write,
+{
+    let mut buffer = ryu_js::Buffer::new();
+    let s = buffer.format(value);
+    writer.write_all(s.as_bytes())
+}
+
+/// Characters escaped in strings beyond those JSON requires.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Escaping {
+    /// Escape every non-ASCII character.
+    ascii: bool,
+    /// Escape `<`, `>`, `&`, U+2028 and U+2029.
+    html: bool,
+}
+
+impl Escaping {
+    fn is_active(self) -> bool {
+        self.ascii || self.html
+    }
+
+    /// Whether a character JSON allows unescaped is written as `\uXXXX`.
+    fn escapes(self, c: char) -> bool {
+        match c {
+            '<' | '>' | '&' | '\u{2028}' | '\u{2029}' if self.html => true,
+            _ => self.ascii && !c.is_ascii(),
+        }
+    }
+}
+
+pub(crate) fn format_escaped_str<W, F>(
+    writer: &mut W,
+    formatter: &mut F,
+    value: &str,
+    escaping: Escaping,
+) -> io::Result<()>
+where
+    W: ?Sized + io::Write,
+    F: ?Sized + Formatter,
+{
+    try!(formatter.begin_string(writer));
+    try!(format_escaped_str_contents(writer, formatter, value, escaping));
+    formatter.end_string(writer)
+}
+
+/// Writes a string of UTF-16 code units, escaping lone surrogates the way
+/// `JSON.stringify` does.
+pub(crate) fn format_escaped_utf16<W, F>(
+    writer: &mut W,
+    formatter: &mut F,
+    units: &[u16],
+    escaping: Escaping,
+) -> io::Result<()>
+where
+    W: ?Sized + io::Write,
+    F: ?Sized + Formatter,
+{
+    try!(formatter.begin_string(writer));
+    let mut run = String::new();
+    for c in char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok(c) => run.push(c),
+            Err(err) => {
+                try!(format_escaped_str_contents(writer, formatter, &run, escaping));
+                run.clear();
+                let escape = format!("\\u{:04x}", err.unpaired_surrogate());
+                try!(formatter.write_string_fragment(writer, &escape));
+            }
+        }
+    }
+    try!(format_escaped_str_contents(writer, formatter, &run, escaping));
+    formatter.end_string(writer)
+}
+
+fn format_escaped_str_contents<W, F>(
+    writer: &mut W,
+    formatter: &mut F,
+    value: &str,
+    escaping: Escaping,
+) -> io::Result<()>
+where
+    W: ?Sized + io::Write,
+    F: ?Sized + Formatter,
+{
+    let bytes = value.as_bytes();
+
+    let mut start = 0;
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        if i < start {
+            // Continuation byte of a character escaped as a whole.
+            continue;
+        }
+
+        let escape = ESCAPE[byte as usize];
+        if escape == 0 {
+            // Continuation bytes belong to the character before them.
+            if !escaping.is_active() || byte & 0xC0 == 0x80 {
+                continue;
+            }
+            let c = value[i..].chars().next().expect("char boundary");
+            if !escaping.escapes(c) {
+                continue;
+            }
 
-/// This structure pretty prints a JSON value to make it human readable.
-#[derive(Clone, Debug)]
-pub struct PrettyV8Formatter<'a> {
-    inner: serde_json::ser::PrettyFormatter<'a>
-}
+            if start < i {
+                try!(formatter.write_string_fragment(writer, &value[start..i]));
+            }
 
-impl<'a> PrettyV8Formatter<'a> {
-    /// Construct a pretty printer formatter that defaults to using two spaces for indentation.
-    pub fn new() -> Self {
-        PrettyV8Formatter {
-            inner: serde_json::ser::PrettyFormatter::new()
+            let mut escaped = String::new();
+            push_utf16_escapes(&mut escaped, c);
+            try!(formatter.write_string_fragment(writer, &escaped));
+            start = i + c.len_utf8();
+            continue;
         }
-    }
 
-    /// Construct a pretty printer formatter that uses the `indent` string for indentation.
-    pub fn with_indent(indent: &'a [u8]) -> Self {
-        PrettyV8Formatter {
-            inner: serde_json::ser::PrettyFormatter::with_indent(indent)
+        if start < i {
+            try!(formatter.write_string_fragment(writer, &value[start..i]));
         }
-    }
-}
 
-impl<'a> Default for PrettyV8Formatter<'a> {
-    fn default() -> Self {
-        PrettyV8Formatter::new()
-    }
-}
+        try!(formatter.write_char_escape(writer, char_escape_from_table(escape, byte)));
 
-impl<'a> Formatter for PrettyV8Formatter<'a> {
-    #[inline]
-    fn write_f32<W: ?Sized>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        let mut buffer = ryu_js::Buffer::new();
-        let s = buffer.format(f64::from(value));
-        writer.write_all(s.as_bytes())
+        start = i + 1;
     }
 
-    #[inline]
-    fn write_f64<W: ?Sized>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        let mut buffer = ryu_js::Buffer::new();
-        let s = buffer.format(value);
-        writer.write_all(s.as_bytes())
+    if start != bytes.len() {
+        try!(formatter.write_string_fragment(writer, &value[start..]));
     }
 
-    #[inline]
-    fn begin_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        self.inner.begin_array(writer)
-    }
+    Ok(())
+}
 
-    #[inline]
-    fn end_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        self.inner.end_array(writer)
-    }
+/// Appends the `\uXXXX` escapes of the UTF-16 code units of a character.
+fn push_utf16_escapes(out: &mut String, c: char) {
+    use std::fmt::Write;
 
-    #[inline]
-    fn begin_array_value<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        self.inner.begin_array_value(writer, first)
+    let mut units = [0; 2];
+    for unit in c.encode_utf16(&mut units) {
+        write!(out, "\\u{unit:04x}").expect("writing to a String cannot fail");
     }
+}
 
-    #[inline]
-    fn end_array_value<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        self.inner.end_array_value(writer)
-    }
+const BB: u8 = b'b'; // \x08
+const TT: u8 = b't'; // \x09
+const NN: u8 = b'n'; // \x0A
+const FF: u8 = b'f'; // \x0C
+const RR: u8 = b'r'; // \x0D
+const QU: u8 = b'"'; // \x22
+const BS: u8 = b'\\'; // \x5C
+const UU: u8 = b'u'; // \x00...\x1F except the ones above
+const __: u8 = 0;
 
-    #[inline]
-    fn begin_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        self.inner.begin_object(writer)
+// Lookup table of escape sequences. A value of b'x' at index i means that byte
+// i is escaped as "\x" in JSON. A value of 0 means that byte i is not escaped.
+static ESCAPE: [u8; 256] = [
+    //   1   2   3   4   5   6   7   8   9   A   B   C   D   E   F
+    UU, UU, UU, UU, UU, UU, UU, UU, BB, TT, NN, UU, FF, RR, UU, UU, // 0
+    UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, // 1
+    __, __, QU, __, __, __, __, __, __, __, __, __, __, __, __, __, // 2
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 3
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 4
+    __, __, __, __, __, __, __, __, __, __, __, __, BS, __, __, __, // 5
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 6
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 7
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 8
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 9
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // A
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // B
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // C
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // D
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // E
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // F
+];
+
+#[inline]
+fn char_escape_from_table(escape: u8, byte: u8) -> CharEscape {
+    match escape {
+        self::BB => CharEscape::Backspace,
+        self::TT => CharEscape::Tab,
+        self::NN => CharEscape::LineFeed,
+        self::FF => CharEscape::FormFeed,
+        self::RR => CharEscape::CarriageReturn,
+        self::QU => CharEscape::Quote,
+        self::BS => CharEscape::ReverseSolidus,
+        self::UU => CharEscape::AsciiControl(byte),
+        _ => unreachable!(),
     }
+}
 
-    #[inline]
-    fn end_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        self.inner.end_object(writer)
+/// Converts the UTF-8 output of a serializer into UTF-16 code units, adding
+/// them to a sink such as a `Vec<u16>`. A character may be split across
+/// writes.
+///
+/// `to_writer_utf16` uses it with the default formatter. It lets any other
+/// formatter write UTF-16 too:
+///
+/// ```edition2018
+/// use serde::Serialize;
+/// use serde_json_v8::ser::{PrettyV8Formatter, Utf16Writer};
+///
+/// let mut json = Vec::new();
+/// let mut ser = serde_json_v8::Serializer::with_formatter(
+///     Utf16Writer::new(&mut json),
+///     PrettyV8Formatter::new(),
+/// );
+/// vec!["😀"].serialize(&mut ser).unwrap();
+/// assert_eq!(json, "[\n  \"😀\"\n]".encode_utf16().collect::<Vec<_>>());
+/// ```
+pub struct Utf16Writer<'a, W: ?Sized + 'a> {
+    sink: &'a mut W,
+    /// The start of a character split across writes.
+    pending: [u8; 4],
+    pending_len: usize,
+}
+
+impl<'a, W> Utf16Writer<'a, W>
+where
+    W: ?Sized + Extend<u16>,
+{
+    /// Creates a writer adding code units to `sink`.
+    pub fn new(sink: &'a mut W) -> Self {
+        Utf16Writer {
+            sink: sink,
+            pending: [0; 4],
+            pending_len: 0,
+        }
     }
 
-    #[inline]
-    fn begin_object_key<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        self.inner.begin_object_key(writer, first)
+    /// Encodes a prefix of `buf` and returns how much of it was used.
+    fn encode(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match str::from_utf8(buf) {
+            Ok(s) => {
+                self.sink.extend(s.encode_utf16());
+                Ok(buf.len())
+            }
+            Err(err) if err.valid_up_to() > 0 => self.encode(&buf[..err.valid_up_to()]),
+            Err(err) => match err.error_len() {
+                Some(_) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+                None => {
+                    self.pending[..buf.len()].copy_from_slice(buf);
+                    self.pending_len = buf.len();
+                    Ok(buf.len())
+                }
+            },
+        }
     }
+}
 
-    #[inline]
-    fn begin_object_value<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        self.inner.begin_object_value(writer)
+impl<W> io::Write for Utf16Writer<'_, W>
+where
+    W: ?Sized + Extend<u16>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.pending_len == 0 {
+            return self.encode(buf);
+        }
+        let char_len = match self.pending[0] {
+            0xF0..=0xFF => 4,
+            0xE0..=0xEF => 3,
+            _ => 2,
+        };
+        let used = cmp::min(char_len - self.pending_len, buf.len());
+        self.pending[self.pending_len..self.pending_len + used].copy_from_slice(&buf[..used]);
+        self.pending_len += used;
+        if self.pending_len == char_len {
+            let pending = self.pending;
+            self.pending_len = 0;
+            try!(self.encode(&pending[..char_len]));
+        }
+        Ok(used)
     }
 
-    #[inline]
-    fn end_object_value<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
-        where
-          W: io::Write,
-    {
-        self.inner.end_object_value(writer)
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
@@ -197,12 +3631,13 @@ impl<'a> Formatter for PrettyV8Formatter<'a> {
 /// # Errors
 ///
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
 #[inline]
-pub fn to_writer<W, T: ?Sized>(writer: W, value: &T) -> Result<()>
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
 where
     W: io::Write,
-    T: Serialize,
+    T: ?Sized + Serialize,
 {
     let mut ser = Serializer::new(writer);
     try!(value.serialize(&mut ser));
@@ -215,28 +3650,51 @@ where
 /// # Errors
 ///
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
 #[inline]
-pub fn to_writer_pretty<W, T: ?Sized>(writer: W, value: &T) -> Result<()>
+pub fn to_writer_pretty<W, T>(writer: W, value: &T) -> Result<()>
 where
     W: io::Write,
-    T: Serialize,
+    T: ?Sized + Serialize,
 {
     let mut ser = Serializer::pretty(writer);
     try!(value.serialize(&mut ser));
     Ok(())
 }
 
+/// Serialize the given data structure as JSON into the IO stream, indented
+/// like `JSON.stringify(value, null, space)`.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_writer_with_space<'a, W, T, S>(writer: W, value: &T, space: S) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+    S: Into<Space<'a>>,
+{
+    let formatter = PrettyV8Formatter::with_space(space.into());
+    let mut ser = Serializer::with_formatter(writer, formatter);
+    try!(value.serialize(&mut ser));
+    Ok(())
+}
+
 /// Serialize the given data structure as a JSON byte vector.
 ///
 /// # Errors
 ///
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
 #[inline]
-pub fn to_vec<T: ?Sized>(value: &T) -> Result<Vec<u8>>
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
 where
-    T: Serialize,
+    T: ?Sized + Serialize,
 {
     let mut writer = Vec::with_capacity(128);
     try!(to_writer(&mut writer, value));
@@ -248,27 +3706,48 @@ where
 /// # Errors
 ///
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
 #[inline]
-pub fn to_vec_pretty<T: ?Sized>(value: &T) -> Result<Vec<u8>>
+pub fn to_vec_pretty<T>(value: &T) -> Result<Vec<u8>>
 where
-    T: Serialize,
+    T: ?Sized + Serialize,
 {
     let mut writer = Vec::with_capacity(128);
     try!(to_writer_pretty(&mut writer, value));
     Ok(writer)
 }
 
+/// Serialize the given data structure as a JSON byte vector, indented like
+/// `JSON.stringify(value, null, space)`.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_vec_with_space<'a, T, S>(value: &T, space: S) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+    S: Into<Space<'a>>,
+{
+    let mut writer = Vec::with_capacity(128);
+    try!(to_writer_with_space(&mut writer, value, space));
+    Ok(writer)
+}
+
 /// Serialize the given data structure as a String of JSON.
 ///
 /// # Errors
 ///
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
 #[inline]
-pub fn to_string<T: ?Sized>(value: &T) -> Result<String>
+pub fn to_string<T>(value: &T) -> Result<String>
 where
-    T: Serialize,
+    T: ?Sized + Serialize,
 {
     let vec = try!(to_vec(value));
     let string = unsafe {
@@ -283,11 +3762,12 @@ where
 /// # Errors
 ///
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to
-/// fail, or if `T` contains a map with non-string keys.
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
 #[inline]
-pub fn to_string_pretty<T: ?Sized>(value: &T) -> Result<String>
+pub fn to_string_pretty<T>(value: &T) -> Result<String>
 where
-    T: Serialize,
+    T: ?Sized + Serialize,
 {
     let vec = try!(to_vec_pretty(value));
     let string = unsafe {
@@ -296,3 +3776,302 @@ where
     };
     Ok(string)
 }
+
+/// Serialize the given data structure as a String of JSON, indented like
+/// `JSON.stringify(value, null, space)`.
+///
+/// ```edition2018
+/// let value = serde_json::json!({"a": [1, 2]});
+/// assert_eq!(
+///     serde_json_v8::to_string_with_space(&value, "\t").unwrap(),
+///     "{\n\t\"a\": [\n\t\t1,\n\t\t2\n\t]\n}"
+/// );
+/// assert_eq!(serde_json_v8::to_string_with_space(&value, 0).unwrap(), r#"{"a":[1,2]}"#);
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_string_with_space<'a, T, S>(value: &T, space: S) -> Result<String>
+where
+    T: ?Sized + Serialize,
+    S: Into<Space<'a>>,
+{
+    let vec = try!(to_vec_with_space(value, space));
+    let string = unsafe {
+        // We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
+}
+
+/// Serialize the given data structure as JSON into a sink of UTF-16 code
+/// units, such as a `Vec<u16>`.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_writer_utf16<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: ?Sized + Extend<u16>,
+    T: ?Sized + Serialize,
+{
+    to_writer(Utf16Writer::new(writer), value)
+}
+
+/// Serialize the given data structure as JSON in UTF-16 code units, the
+/// representation of JavaScript strings.
+///
+/// ```edition2018
+/// let value = serde_json::json!({"emoji": "😀"});
+/// let json: Vec<u16> = r#"{"emoji":"😀"}"#.encode_utf16().collect();
+/// assert_eq!(serde_json_v8::to_utf16(&value).unwrap(), json);
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_utf16<T>(value: &T) -> Result<Vec<u16>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = Vec::with_capacity(128);
+    try!(to_writer_utf16(&mut writer, value));
+    Ok(writer)
+}
+
+/// Serialize the given data structure as JSON into the IO stream, passing it
+/// through a replacer like `JSON.stringify(value, replacer)`. Nothing is
+/// written if the replacer drops the root value.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_writer_with_replacer<W, T>(writer: W, value: &T, mut replacer: Replacer) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let mut ser = Serializer::new(writer);
+    try!(ser.serialize_with_replacer(value, &mut replacer));
+    Ok(())
+}
+
+/// Serialize the given data structure as a JSON byte vector, passing it
+/// through a replacer like `JSON.stringify(value, replacer)`. Returns `None`
+/// if the replacer drops the root value.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_vec_with_replacer<T>(value: &T, mut replacer: Replacer) -> Result<Option<Vec<u8>>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut ser = Serializer::new(Vec::with_capacity(128));
+    if try!(ser.serialize_with_replacer(value, &mut replacer)) {
+        Ok(Some(ser.into_inner()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Serialize the given data structure as a String of JSON, passing it
+/// through a replacer like `JSON.stringify(value, replacer)`. Returns `None`
+/// if the replacer drops the root value.
+///
+/// ```edition2018
+/// use serde::Serialize;
+/// use serde_json_v8::ser::Replacer;
+///
+/// #[derive(Serialize)]
+/// struct Account {
+///     user: &'static str,
+///     password: &'static str,
+///     tags: Vec<&'static str>,
+/// }
+///
+/// let value = Account { user: "ann", password: "hunter2", tags: vec!["a"] };
+/// let redact = Replacer::function(|path, value| match path.last() {
+///     Some(key) if key == "password" => None,
+///     _ => Some(value),
+/// });
+/// assert_eq!(
+///     serde_json_v8::to_string_with_replacer(&value, redact).unwrap().unwrap(),
+///     r#"{"user":"ann","tags":["a"]}"#
+/// );
+///
+/// let allow = Replacer::allowlist(vec!["tags", "user"]);
+/// assert_eq!(
+///     serde_json_v8::to_string_with_replacer(&value, allow).unwrap().unwrap(),
+///     r#"{"tags":["a"],"user":"ann"}"#
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_string_with_replacer<T>(value: &T, replacer: Replacer) -> Result<Option<String>>
+where
+    T: ?Sized + Serialize,
+{
+    let vec = try!(to_vec_with_replacer(value, replacer));
+    Ok(vec.map(|vec| unsafe {
+        // We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    }))
+}
+
+/// Serialize the given data structure as canonical JSON (RFC 8785) into the
+/// IO stream.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, if `T` contains a map with keys that are maps, structs or enum
+/// variants with data, or if `T` contains a number that is not finite. Other
+/// keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_canonical_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let mut ser = Serializer::canonical(writer);
+    try!(value.serialize(&mut ser));
+    Ok(())
+}
+
+/// Serialize the given data structure as a canonical JSON (RFC 8785) byte
+/// vector.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, if `T` contains a map with keys that are maps, structs or enum
+/// variants with data, or if `T` contains a number that is not finite. Other
+/// keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_canonical_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = Vec::with_capacity(128);
+    try!(to_canonical_writer(&mut writer, value));
+    Ok(writer)
+}
+
+/// Serialize the given data structure as a String of canonical JSON (RFC
+/// 8785).
+///
+/// ```edition2018
+/// let value = serde_json::json!({"b": [1e21, 9007199254740993u64], "a": "\u{7f}", "\u{e9}": 1.0});
+/// assert_eq!(
+///     serde_json_v8::to_canonical_string(&value).unwrap(),
+///     "{\"a\":\"\u{7f}\",\"b\":[1e+21,9007199254740992],\"\u{e9}\":1}"
+/// );
+/// assert!(serde_json_v8::to_canonical_string(&f64::NAN).is_err());
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, if `T` contains a map with keys that are maps, structs or enum
+/// variants with data, or if `T` contains a number that is not finite. Other
+/// keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_canonical_string<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let vec = try!(to_canonical_vec(value));
+    let string = unsafe {
+        // We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
+}
+
+/// Serialize the given data structure as JSON into the IO stream, escaping
+/// characters that are unsafe inside an HTML `<script>` element.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_writer_html_safe<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let mut ser = Serializer::html_safe(writer);
+    try!(value.serialize(&mut ser));
+    Ok(())
+}
+
+/// Serialize the given data structure as a JSON byte vector, escaping
+/// characters that are unsafe inside an HTML `<script>` element.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_vec_html_safe<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = Vec::with_capacity(128);
+    try!(to_writer_html_safe(&mut writer, value));
+    Ok(writer)
+}
+
+/// Serialize the given data structure as a String of JSON, escaping
+/// characters that are unsafe inside an HTML `<script>` element.
+///
+/// ```edition2018
+/// let state = serde_json::json!({"html": "<b>a & b</b>", "n": 1e21});
+/// assert_eq!(
+///     serde_json_v8::to_string_html_safe(&state).unwrap(),
+///     r#"{"html":"\u003cb\u003ea \u0026 b\u003c/b\u003e","n":1e+21}"#
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with keys that are maps, structs or enum
+/// variants with data. Other keys are coerced to strings, see `KeyPolicy`.
+#[inline]
+pub fn to_string_html_safe<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let vec = try!(to_vec_html_safe(value));
+    let string = unsafe {
+        // We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
+}
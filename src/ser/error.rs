@@ -0,0 +1,235 @@
+//! When serializing JSON goes wrong.
+
+use std::error;
+use std::fmt::{self, Debug, Display};
+use std::io;
+use std::result;
+
+use serde::ser;
+use serde_json;
+
+/// This type represents all possible errors that can occur when serializing
+/// JSON data, and is the crate's `serde_json_v8::Error`. It converts to and
+/// from `serde_json::Error`, which is what deserialization reports, so `?`
+/// turns either into the other.
+///
+/// Besides its message, it tells what kind of failure happened and where in
+/// the serialized value:
+///
+/// ```edition2018
+/// use serde_json_v8::ser::{ErrorKind, IntegerPolicy, Serializer};
+/// use serde::Serialize;
+///
+/// let value = serde_json::json!({"users": [{}, {}, {}, {"id": 9007199254740993u64}]});
+/// let mut ser = Serializer::new(Vec::new());
+/// ser.set_integer_policy(IntegerPolicy::Error);
+/// let err = value.serialize(&mut ser).unwrap_err();
+///
+/// assert_eq!(err.kind(), ErrorKind::UnsafeInteger);
+/// assert_eq!(err.path(), Some("$.users[3].id"));
+/// assert_eq!(
+///     err.to_string(),
+///     "9007199254740993 is outside of the safe integer range at $.users[3].id"
+/// );
+/// ```
+///
+/// Errors from parsing convert into it:
+///
+/// ```edition2018
+/// use serde_json::Value;
+///
+/// fn normalize(text: &str) -> serde_json_v8::Result<String> {
+///     let value: Value = serde_json_v8::from_str(text)?;
+///     serde_json_v8::to_string(&value)
+/// }
+///
+/// assert_eq!(normalize("[1.0, -0]").unwrap(), "[1,0]");
+/// assert_eq!(normalize("[1,]").unwrap_err().kind(), serde_json_v8::ErrorKind::Custom);
+/// ```
+pub struct Error {
+    /// This `Box` allows us to keep the size of `Error` as small as possible.
+    err: Box<ErrorImpl>,
+}
+
+/// Alias for a `Result` with the error type `serde_json_v8::Error`.
+pub type Result<T> = result::Result<T, Error>;
+
+struct ErrorImpl {
+    kind: ErrorKind,
+    code: ErrorCode,
+    path: Option<String>,
+}
+
+enum ErrorCode {
+    Message(Box<str>),
+    Io(io::Error),
+}
+
+/// Categorizes the cause of a `serde_json_v8::Error`.
+///
+/// More kinds may be added, so matches need a wildcard arm.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The error was caused by a failure to write bytes on an IO stream.
+    Io,
+
+    /// A `NaN` or infinite number was serialized under
+    /// `NumberPolicy::Error`.
+    NonFiniteNumber,
+
+    /// An integer outside of the safe integer range was serialized under
+    /// `IntegerPolicy::Error`.
+    UnsafeInteger,
+
+    /// A map key was not a string, nor a value written as a string.
+    KeyMustBeAString,
+
+    /// Any other error, like one raised by an implementation of `Serialize`
+    /// or converted from serde_json's error.
+    Custom,
+}
+
+impl Error {
+    /// Categorizes the cause of this error.
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        self.err.kind
+    }
+
+    /// The path of the value that failed to serialize, like `$.users[3].id`.
+    ///
+    /// When a map key fails to serialize, this is the path of the map. It is
+    /// `None` for IO errors, and when the error did not happen inside of an
+    /// array or an object.
+    #[must_use]
+    pub fn path(&self) -> Option<&str> {
+        self.err.path.as_deref()
+    }
+
+    pub(crate) fn new<T>(kind: ErrorKind, msg: T) -> Self
+    where
+        T: Display,
+    {
+        Error {
+            err: Box::new(ErrorImpl {
+                kind: kind,
+                code: ErrorCode::Message(msg.to_string().into_boxed_str()),
+                path: None,
+            }),
+        }
+    }
+
+    /// Wraps an IO error, unwrapping the errors of this type that a
+    /// formatter raised through `io::Error`.
+    pub(crate) fn io(error: io::Error) -> Self {
+        if error.get_ref().and_then(|inner| inner.downcast_ref::<Error>()).is_some() {
+            let inner = error.into_inner().and_then(|inner| inner.downcast().ok());
+            return *inner.expect("checked to be a serialization error");
+        }
+        Error {
+            err: Box::new(ErrorImpl {
+                kind: ErrorKind::Io,
+                code: ErrorCode::Io(error),
+                path: None,
+            }),
+        }
+    }
+
+    /// Records where the error happened, unless it is already known or the
+    /// error is not about a value.
+    pub(crate) fn with_path<P>(mut self, path: P) -> Self
+    where
+        P: Display,
+    {
+        if self.err.path.is_none() && self.err.kind != ErrorKind::Io {
+            self.err.path = Some(path.to_string());
+        }
+        self
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    /// Convert a `serde_json::Error` into a `serde_json_v8::Error`.
+    ///
+    /// IO errors keep their kind, and any other error becomes a `Custom` one
+    /// with the same message.
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_io() {
+            Error::io(io::Error::from(error))
+        } else {
+            Error::new(ErrorKind::Custom, error)
+        }
+    }
+}
+
+impl From<Error> for serde_json::Error {
+    /// Convert a `serde_json_v8::Error` into a `serde_json::Error`.
+    ///
+    /// IO errors keep their category, and any other error becomes a data
+    /// error whose message includes the path.
+    fn from(error: Error) -> Self {
+        match error.err.code {
+            ErrorCode::Io(err) => serde_json::Error::io(err),
+            ErrorCode::Message(_) => ser::Error::custom(error),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    /// Convert a `serde_json_v8::Error` into an `io::Error`.
+    ///
+    /// Errors other than IO errors are turned into `InvalidData` IO errors.
+    fn from(error: Error) -> Self {
+        match error.err.code {
+            ErrorCode::Io(err) => err,
+            ErrorCode::Message(_) => io::Error::new(io::ErrorKind::InvalidData, error),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.err.code {
+            ErrorCode::Io(ref err) => Some(err),
+            ErrorCode::Message(_) => None,
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorCode::Message(ref msg) => f.write_str(msg),
+            ErrorCode::Io(ref err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.err.path {
+            Some(ref path) => write!(f, "{} at {}", self.err.code, path),
+            None => Display::fmt(&self.err.code, f),
+        }
+    }
+}
+
+impl Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Error({:?}, {:?}, path: {:?})",
+            self.err.kind,
+            self.err.code.to_string(),
+            self.err.path
+        )
+    }
+}
+
+impl ser::Error for Error {
+    #[cold]
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::new(ErrorKind::Custom, msg)
+    }
+}
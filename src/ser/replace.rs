@@ -0,0 +1,701 @@
+//! Replacers, like the second argument of `JSON.stringify`.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io;
+
+use serde::ser::{self, Serialize};
+use serde_json::{self, Map, Number, Value};
+
+use js_string::{utf16_from_bytes, JS_STRING_TOKEN};
+
+use super::{
+    Error, Formatter, MapKeySerializer, Result, Serializer, NUMBER_TOKEN, RAW_VALUE_TOKEN,
+};
+
+/// A replacer, like the second argument of `JSON.stringify`.
+#[allow(clippy::type_complexity)]
+pub enum Replacer<'a> {
+    /// Called top-down on every value with the path of property names leading
+    /// to it, the root having an empty path and array indices appearing as
+    /// decimal strings. The returned value is written in place of the
+    /// original. Returning `None` drops an object property and turns an array
+    /// element into `null`, as returning `undefined` does in JavaScript.
+    ///
+    /// Numbers that `Value` cannot hold, like `NaN`, are passed as `null`.
+    /// The parts of the value returned unchanged are written as the
+    /// original, with its numbers and the order of its properties.
+    Function(Box<dyn FnMut(&[String], Value) -> Option<Value> + 'a>),
+    /// Write only the listed properties of each object, in the order of the
+    /// list. Arrays are written in full.
+    Allowlist(Vec<String>),
+}
+
+impl<'a> Replacer<'a> {
+    /// Creates a replacer from a function of the path and value.
+    pub fn function<F>(function: F) -> Self
+    where
+        F: FnMut(&[String], Value) -> Option<Value> + 'a,
+    {
+        Replacer::Function(Box::new(function))
+    }
+
+    /// Creates a replacer keeping only the given property names. Duplicate
+    /// names are ignored.
+    pub fn allowlist<I>(names: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut list: Vec<String> = Vec::new();
+        for name in names {
+            let name = name.into();
+            if !list.contains(&name) {
+                list.push(name);
+            }
+        }
+        Replacer::Allowlist(list)
+    }
+
+    /// Applies the replacer to `node` and to everything it contains, or
+    /// returns `None` if the node is dropped.
+    fn apply(&mut self, path: &mut Vec<String>, node: Node) -> Option<Node> {
+        match *self {
+            Replacer::Function(ref mut function) => {
+                // The tree is converted once; each call hands its children
+                // over to the calls below it.
+                let value = node.to_value();
+                replace(&mut **function, path, Some(node), value)
+            }
+            Replacer::Allowlist(ref names) => Some(allow(names, node)),
+        }
+    }
+}
+
+/// Calls `function` on `value`, then on the children of what it returns, and
+/// builds the node to write. `original` is the node `value` was converted
+/// from, if any: the primitives the function leaves unchanged are written as
+/// they were, and the properties it keeps stay in their original order.
+fn replace<F>(
+    function: &mut F,
+    path: &mut Vec<String>,
+    original: Option<Node>,
+    value: Value,
+) -> Option<Node>
+where
+    F: ?Sized + FnMut(&[String], Value) -> Option<Value>,
+{
+    let replaced = function(path, value)?;
+    Some(match replaced {
+        Value::Array(items) => {
+            let mut originals = match original {
+                Some(Node::Array(nodes)) => nodes.into_iter(),
+                _ => Vec::new().into_iter(),
+            };
+            let mut nodes = Vec::with_capacity(items.len());
+            for (index, item) in items.into_iter().enumerate() {
+                path.push(index.to_string());
+                let node = replace(function, path, originals.next(), item);
+                path.pop();
+                nodes.push(node.unwrap_or(Node::Value(Value::Null)));
+            }
+            Node::Array(nodes)
+        }
+        Value::Object(map) => {
+            let originals = match original {
+                Some(Node::Object(entries)) => entries,
+                _ => Vec::new(),
+            };
+            let (keys, values): (Vec<String>, Vec<Value>) = map.into_iter().unzip();
+            let mut values: Vec<Option<Value>> = values.into_iter().map(Some).collect();
+            let mut positions: HashMap<&str, usize> = keys
+                .iter()
+                .enumerate()
+                .map(|(index, key)| (key.as_str(), index))
+                .collect();
+            let mut pending = Vec::with_capacity(keys.len());
+            for (key, node) in originals {
+                if let Some(index) = positions.remove(key.as_str()) {
+                    pending.push((index, Some(node)));
+                }
+            }
+            for (index, key) in keys.iter().enumerate() {
+                if positions.contains_key(key.as_str()) {
+                    pending.push((index, None));
+                }
+            }
+            let mut entries = Vec::with_capacity(pending.len());
+            for (index, node) in pending {
+                let value = values[index].take().unwrap_or(Value::Null);
+                path.push(keys[index].clone());
+                let node = replace(function, path, node, value);
+                let key = path.pop().unwrap_or_default();
+                if let Some(node) = node {
+                    entries.push((key, node));
+                }
+            }
+            Node::Object(entries)
+        }
+        primitive => match original {
+            Some(node) if node.is_primitive() && node.to_value() == primitive => node,
+            _ => Node::Value(primitive),
+        },
+    })
+}
+
+/// Keeps only the listed properties of every object within `node`.
+fn allow(names: &[String], node: Node) -> Node {
+    match node {
+        Node::Array(items) => Node::Array(items.into_iter().map(|item| allow(names, item)).collect()),
+        Node::Object(entries) => Node::Object(
+            names
+                .iter()
+                .filter_map(|name| {
+                    let index = entries.iter().rposition(|entry| entry.0 == *name)?;
+                    Some((name.clone(), allow(names, entries[index].1.clone())))
+                })
+                .collect(),
+        ),
+        node => node,
+    }
+}
+
+impl<W, F> Serializer<W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    /// Serializes `value` passed through a replacer, like
+    /// `JSON.stringify(value, replacer)`, with the settings of this
+    /// serializer. Returns `false` and writes nothing if the replacer drops
+    /// the root value.
+    ///
+    /// ```edition2018
+    /// use serde_json_v8::ser::{KeyOrder, Replacer};
+    ///
+    /// let value = serde_json::json!({"b": 1, "1": 2, "a": 3});
+    /// let mut ser = serde_json_v8::Serializer::new(Vec::new());
+    /// ser.set_key_order(KeyOrder::V8);
+    /// let mut allow = Replacer::allowlist(vec!["b", "1"]);
+    /// assert!(ser.serialize_with_replacer(&value, &mut allow).unwrap());
+    /// assert_eq!(ser.into_inner(), br#"{"1":2,"b":1}"#);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Serialization can fail if `T`'s implementation of `Serialize` decides
+    /// to fail, or if the settings of this serializer reject a value.
+    pub fn serialize_with_replacer<T>(&mut self, value: &T, replacer: &mut Replacer) -> Result<bool>
+    where
+        T: ?Sized + Serialize,
+    {
+        let node = try!(value.serialize(NodeSerializer {
+            key: self.map_key_serializer(),
+            payload: false,
+        }));
+        match replacer.apply(&mut Vec::new(), node) {
+            Some(node) => {
+                try!(node.serialize(self));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// A value as it was serialized, keeping the order of object properties and
+/// the numbers that `Value` cannot hold.
+#[derive(Clone)]
+enum Node {
+    /// A null, boolean, 64-bit integer or string.
+    Value(Value),
+    F32(f32),
+    F64(f64),
+    I128(i128),
+    U128(u128),
+    /// One of serde_json's private structs, holding the text of a number or
+    /// of a raw value.
+    Verbatim(&'static str, String),
+    /// The big-endian UTF-16 code units of a `JsString` with lone surrogates.
+    JsString(Vec<u8>),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+impl Node {
+    fn is_primitive(&self) -> bool {
+        !matches!(*self, Node::Array(_) | Node::Object(_))
+    }
+
+    /// Converts the node into the value shown to a replacer function.
+    fn to_value(&self) -> Value {
+        match *self {
+            Node::Value(ref value) => value.clone(),
+            Node::F32(value) => float_value(f64::from(value)),
+            Node::F64(value) => float_value(value),
+            Node::I128(value) => match i64::try_from(value) {
+                Ok(value) => Value::from(value),
+                Err(_) => float_value(value as f64),
+            },
+            Node::U128(value) => match u64::try_from(value) {
+                Ok(value) => Value::from(value),
+                Err(_) => float_value(value as f64),
+            },
+            Node::Verbatim(_, ref text) => serde_json::from_str(text).unwrap_or(Value::Null),
+            Node::JsString(ref bytes) => match utf16_from_bytes(bytes) {
+                Some(units) => Value::String(String::from_utf16_lossy(&units)),
+                None => Value::Null,
+            },
+            Node::Array(ref items) => Value::Array(items.iter().map(Node::to_value).collect()),
+            Node::Object(ref entries) => {
+                let mut map = Map::new();
+                for (key, value) in entries {
+                    map.insert(key.clone(), value.to_value());
+                }
+                Value::Object(map)
+            }
+        }
+    }
+}
+
+fn float_value(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+impl Serialize for Node {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct};
+
+        match *self {
+            Node::Value(ref value) => value.serialize(serializer),
+            Node::F32(value) => serializer.serialize_f32(value),
+            Node::F64(value) => serializer.serialize_f64(value),
+            Node::I128(value) => serializer.serialize_i128(value),
+            Node::U128(value) => serializer.serialize_u128(value),
+            Node::Verbatim(token, ref text) => {
+                let mut s = try!(serializer.serialize_struct(token, 1));
+                try!(s.serialize_field(token, text));
+                s.end()
+            }
+            Node::JsString(ref bytes) => {
+                serializer.serialize_newtype_struct(JS_STRING_TOKEN, &Bytes(bytes))
+            }
+            Node::Array(ref items) => {
+                let mut seq = try!(serializer.serialize_seq(Some(items.len())));
+                for item in items {
+                    try!(seq.serialize_element(item));
+                }
+                seq.end()
+            }
+            Node::Object(ref entries) => {
+                let mut map = try!(serializer.serialize_map(Some(entries.len())));
+                for (key, value) in entries {
+                    try!(map.serialize_entry(key, value));
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Captures a value as a `Node`, turning map keys into property names like
+/// the serializer it is created from.
+#[derive(Clone, Copy)]
+struct NodeSerializer {
+    key: MapKeySerializer,
+    /// Whether this captures the payload of a `JsString`, which only comes
+    /// as bytes when the serializer is not human-readable.
+    payload: bool,
+}
+
+impl ser::Serializer for NodeSerializer {
+    type Ok = Node;
+    type Error = Error;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeVariant<SerializeVec>;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeVariant<SerializeMap>;
+
+    fn is_human_readable(&self) -> bool {
+        !self.payload
+    }
+
+    fn serialize_bool(self, value: bool) -> Result<Node> {
+        Ok(Node::Value(Value::Bool(value)))
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<Node> {
+        self.serialize_i64(i64::from(value))
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<Node> {
+        self.serialize_i64(i64::from(value))
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<Node> {
+        self.serialize_i64(i64::from(value))
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<Node> {
+        Ok(Node::Value(Value::from(value)))
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<Node> {
+        Ok(Node::I128(value))
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<Node> {
+        self.serialize_u64(u64::from(value))
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<Node> {
+        self.serialize_u64(u64::from(value))
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<Node> {
+        self.serialize_u64(u64::from(value))
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<Node> {
+        Ok(Node::Value(Value::from(value)))
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<Node> {
+        Ok(Node::U128(value))
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<Node> {
+        Ok(Node::F32(value))
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<Node> {
+        Ok(Node::F64(value))
+    }
+
+    fn serialize_char(self, value: char) -> Result<Node> {
+        Ok(Node::Value(Value::String(value.to_string())))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Node> {
+        Ok(Node::Value(Value::String(value.to_owned())))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Node> {
+        Ok(Node::Array(
+            value.iter().map(|&byte| Node::Value(Value::from(byte))).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Node> {
+        Ok(Node::Value(Value::Null))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Node>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Node> {
+        Ok(Node::Value(Value::Null))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Node> {
+        Ok(Node::Value(Value::Null))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Node> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Node>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name != JS_STRING_TOKEN {
+            return value.serialize(self);
+        }
+        match try!(value.serialize(NodeSerializer {
+            payload: true,
+            ..self
+        })) {
+            Node::Array(bytes) => Ok(Node::JsString(
+                bytes
+                    .iter()
+                    .filter_map(|byte| match *byte {
+                        Node::Value(Value::Number(ref n)) => {
+                            n.as_u64().and_then(|n| u8::try_from(n).ok())
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+            )),
+            _ => Err(ser::Error::custom("expected bytes in private JsString struct")),
+        }
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = try!(value.serialize(self));
+        Ok(Node::Object(vec![(variant.to_owned(), value)]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec> {
+        Ok(SerializeVec {
+            ser: self,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeVec>> {
+        Ok(SerializeVariant {
+            variant: variant,
+            inner: try!(self.serialize_seq(Some(len))),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap> {
+        Ok(SerializeMap {
+            ser: self,
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<SerializeStruct> {
+        if name == NUMBER_TOKEN || name == RAW_VALUE_TOKEN {
+            Ok(SerializeStruct::Verbatim(self, name, None))
+        } else {
+            self.serialize_map(Some(len)).map(SerializeStruct::Map)
+        }
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeMap>> {
+        Ok(SerializeVariant {
+            variant: variant,
+            inner: try!(self.serialize_map(Some(len))),
+        })
+    }
+}
+
+struct SerializeVec {
+    ser: NodeSerializer,
+    items: Vec<Node>,
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let item = try!(value.serialize(self.ser));
+        self.items.push(item);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(Node::Array(self.items))
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Node> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Node> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeMap {
+    ser: NodeSerializer,
+    entries: Vec<(String, Node)>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key = Some(try!(key.serialize(self.ser.key)));
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.key.take() {
+            Some(key) => {
+                let value = try!(value.serialize(self.ser));
+                self.entries.push((key, value));
+                Ok(())
+            }
+            None => Err(ser::Error::custom("serialize_value called before serialize_key")),
+        }
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(Node::Object(self.entries))
+    }
+}
+
+enum SerializeStruct {
+    Map(SerializeMap),
+    /// One of serde_json's private structs, with its text once known.
+    Verbatim(NodeSerializer, &'static str, Option<String>),
+}
+
+impl ser::SerializeStruct for SerializeStruct {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match *self {
+            SerializeStruct::Map(ref mut map) => ser::SerializeMap::serialize_entry(map, key, value),
+            SerializeStruct::Verbatim(ser, _, ref mut text) => match try!(value.serialize(ser)) {
+                Node::Value(Value::String(s)) => {
+                    *text = Some(s);
+                    Ok(())
+                }
+                _ => Err(ser::Error::custom("expected a string in private serde_json struct")),
+            },
+        }
+    }
+
+    fn end(self) -> Result<Node> {
+        match self {
+            SerializeStruct::Map(map) => ser::SerializeMap::end(map),
+            SerializeStruct::Verbatim(_, token, Some(text)) => Ok(Node::Verbatim(token, text)),
+            SerializeStruct::Verbatim(_, _, None) => {
+                Err(ser::Error::custom("expected a field in private serde_json struct"))
+            }
+        }
+    }
+}
+
+/// Wraps the content of an enum variant in an object, the way serde_json
+/// represents externally tagged variants.
+struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeVec> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Node> {
+        let value = try!(ser::SerializeSeq::end(self.inner));
+        Ok(Node::Object(vec![(self.variant.to_owned(), value)]))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeMap> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeMap::serialize_entry(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Node> {
+        let value = try!(ser::SerializeMap::end(self.inner));
+        Ok(Node::Object(vec![(self.variant.to_owned(), value)]))
+    }
+}
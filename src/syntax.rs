@@ -0,0 +1,503 @@
+//! Syntax error messages worded the way V8's `JSON.parse` words them.
+//!
+//! The errors of the deserializers say where the input went wrong with
+//! serde_json's wording, `expected value at line 1 column 5`. A `ParseError`
+//! wraps such an error together with the input, and renders the message
+//! `JSON.parse` would throw for the same input, with positions counted in
+//! UTF-16 code units like JavaScript counts them:
+//!
+//! ```edition2018
+//! use serde_json::Value;
+//! use serde_json_v8::syntax::{MessageVersion, ParseError};
+//!
+//! let text = r#"{"a":1,}"#;
+//! let error = serde_json_v8::from_str::<Value>(text).unwrap_err();
+//! let error = ParseError::new(error, text);
+//! assert_eq!(
+//!     error.to_string(),
+//!     "Expected double-quoted property name in JSON at position 7 (line 1 column 8)"
+//! );
+//! assert_eq!(
+//!     error.message(MessageVersion::Legacy),
+//!     "Unexpected token } in JSON at position 7"
+//! );
+//!
+//! let text = "[1, 2,]";
+//! let error = ParseError::new(serde_json_v8::from_str::<Value>(text).unwrap_err(), text);
+//! assert_eq!(
+//!     error.message(MessageVersion::Contextual),
+//!     r#"Unexpected token ']', "[1, 2,]" is not valid JSON"#
+//! );
+//! ```
+
+use std::cmp;
+use std::error;
+use std::fmt::{self, Display};
+
+use serde_json::error::Error;
+
+/// Which generation of V8 messages to render.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MessageVersion {
+    /// The messages of Node.js 18 and earlier, naming the unexpected token:
+    /// `Unexpected token } in JSON at position 7`.
+    Legacy,
+    /// The messages of Node.js 20, which describe what was expected and
+    /// quote the input around unexpected tokens:
+    /// `Unexpected token ']', "[1,]" is not valid JSON`.
+    Contextual,
+    /// The messages of Node.js 22 and later, which add the line and column
+    /// to positions: `Unexpected non-whitespace character after JSON at
+    /// position 2 (line 1 column 3)`. This is the default.
+    #[default]
+    LineColumn,
+}
+
+/// Why V8 stopped, for the errors it reports with a dedicated message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Reason {
+    PropertyNameOrBrace,
+    DoubleQuotedPropertyName,
+    ColonAfterPropertyName,
+    CommaOrBraceAfterValue,
+    CommaOrBracketAfterElement,
+    NonWhitespaceAfterJson,
+    BadControlCharacter,
+    UnterminatedString,
+    BadEscapedCharacter,
+    BadUnicodeEscape,
+    NoNumberAfterMinusSign,
+    UnterminatedFractionalNumber,
+    ExponentPartMissingNumber,
+}
+
+impl Reason {
+    fn description(self) -> &'static str {
+        match self {
+            Reason::PropertyNameOrBrace => "Expected property name or '}' in JSON",
+            Reason::DoubleQuotedPropertyName => "Expected double-quoted property name in JSON",
+            Reason::ColonAfterPropertyName => "Expected ':' after property name in JSON",
+            Reason::CommaOrBraceAfterValue => "Expected ',' or '}' after property value in JSON",
+            Reason::CommaOrBracketAfterElement => "Expected ',' or ']' after array element in JSON",
+            Reason::NonWhitespaceAfterJson => "Unexpected non-whitespace character after JSON",
+            Reason::BadControlCharacter => "Bad control character in string literal in JSON",
+            Reason::UnterminatedString => "Unterminated string in JSON",
+            Reason::BadEscapedCharacter => "Bad escaped character in JSON",
+            Reason::BadUnicodeEscape => "Bad Unicode escape in JSON",
+            Reason::NoNumberAfterMinusSign => "No number after minus sign in JSON",
+            Reason::UnterminatedFractionalNumber => "Unterminated fractional number in JSON",
+            Reason::ExponentPartMissingNumber => "Exponent part is missing a number in JSON",
+        }
+    }
+}
+
+/// The token V8 found where the input went wrong.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    End,
+    Number,
+    String,
+    Char(char),
+}
+
+/// The input around an unexpected token, as V8 quotes it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Context {
+    /// The whole input, which is short.
+    Whole(String),
+    Start(String),
+    Middle(String),
+    End(String),
+}
+
+/// How many UTF-16 code units of context V8 quotes on each side of an
+/// unexpected token.
+const MAX_CONTEXT: usize = 10;
+
+/// Inputs that `JSON.parse` commonly receives by mistake, for which V8 quotes
+/// the whole input rather than the unexpected token.
+const SPECIAL_INPUTS: &[&str] = &["[object Object]", "undefined", "Infinity", "NaN"];
+
+#[derive(Clone, Debug)]
+struct Syntax {
+    token: Token,
+    reason: Option<Reason>,
+    position: usize,
+    line: usize,
+    column: usize,
+    context: Context,
+}
+
+impl Syntax {
+    fn message(&self, version: MessageVersion) -> String {
+        let at = match version {
+            MessageVersion::LineColumn => format!(
+                "at position {} (line {} column {})",
+                self.position, self.line, self.column
+            ),
+            _ => format!("at position {}", self.position),
+        };
+        match (version, self.reason, &self.token) {
+            (MessageVersion::Legacy, _, &Token::Char(c)) => {
+                format!("Unexpected token {c} in JSON {at}")
+            }
+            (MessageVersion::Legacy, _, _) | (_, None, _) => match self.token {
+                Token::End => "Unexpected end of JSON input".to_owned(),
+                Token::Number => format!("Unexpected number in JSON {at}"),
+                Token::String => format!("Unexpected string in JSON {at}"),
+                Token::Char(c) => match self.context {
+                    Context::Whole(ref source) if SPECIAL_INPUTS.contains(&source.as_str()) => {
+                        format!("\"{source}\" is not valid JSON")
+                    }
+                    Context::Whole(ref source) => {
+                        format!("Unexpected token '{c}', \"{source}\" is not valid JSON")
+                    }
+                    Context::Start(ref source) => {
+                        format!("Unexpected token '{c}', \"{source}\"... is not valid JSON")
+                    }
+                    Context::Middle(ref source) => format!(
+                        "Unexpected token '{c}', ...\"{source}\"... is not valid JSON"
+                    ),
+                    Context::End(ref source) => {
+                        format!("Unexpected token '{c}', ...\"{source}\" is not valid JSON")
+                    }
+                },
+            },
+            (_, Some(reason), _) => format!("{} {}", reason.description(), at),
+        }
+    }
+}
+
+/// An error from parsing JSON text, which renders the message V8's
+/// `JSON.parse` throws for the same text.
+///
+/// Errors that are not about the syntax of the input, such as a value of the
+/// wrong type, keep the message of the wrapped error.
+#[derive(Debug)]
+pub struct ParseError {
+    error: Error,
+    syntax: Option<Syntax>,
+}
+
+impl ParseError {
+    /// Wraps an error from parsing `input`.
+    #[must_use]
+    pub fn new(error: Error, input: &str) -> Self {
+        ParseError {
+            error: error,
+            syntax: check(input).err().map(|failure| locate(input, failure)),
+        }
+    }
+
+    /// Returns the position of the syntax error in UTF-16 code units, which
+    /// is the length of the input for an unexpected end of input.
+    #[must_use]
+    pub fn position(&self) -> Option<usize> {
+        self.syntax.as_ref().map(|syntax| syntax.position)
+    }
+
+    /// Returns the line of the syntax error, starting at 1.
+    #[must_use]
+    pub fn line(&self) -> Option<usize> {
+        self.syntax.as_ref().map(|syntax| syntax.line)
+    }
+
+    /// Returns the column of the syntax error in UTF-16 code units, starting
+    /// at 1.
+    #[must_use]
+    pub fn column(&self) -> Option<usize> {
+        self.syntax.as_ref().map(|syntax| syntax.column)
+    }
+
+    /// Renders the message V8 of the given version throws.
+    #[must_use]
+    pub fn message(&self, version: MessageVersion) -> String {
+        match self.syntax {
+            Some(ref syntax) => syntax.message(version),
+            None => self.error.to_string(),
+        }
+    }
+
+    /// Returns the wrapped error.
+    #[must_use]
+    pub fn inner(&self) -> &Error {
+        &self.error
+    }
+
+    /// Unwraps the wrapped error.
+    #[must_use]
+    pub fn into_inner(self) -> Error {
+        self.error
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message(MessageVersion::default()))
+    }
+}
+
+impl error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        error.error
+    }
+}
+
+/// Where and why V8 stops parsing, as a byte offset into the input.
+#[derive(Clone, Copy)]
+struct Failure {
+    index: usize,
+    reason: Option<Reason>,
+}
+
+/// Checks the input the way V8 does, finding the first syntax error. Nesting
+/// is tracked on the heap so that deep inputs cannot overflow the stack.
+fn check(input: &str) -> Result<(), Failure> {
+    let mut checker = Checker {
+        input: input.as_bytes(),
+        index: 0,
+    };
+    let mut containers = Vec::new();
+    'value: loop {
+        checker.skip_whitespace();
+        match checker.peek() {
+            Some(b'"') => try!(checker.string()),
+            Some(b'-' | b'0'..=b'9') => try!(checker.number()),
+            Some(b't') => try!(checker.literal(b"true")),
+            Some(b'f') => try!(checker.literal(b"false")),
+            Some(b'n') => try!(checker.literal(b"null")),
+            Some(b'[') => {
+                checker.index += 1;
+                checker.skip_whitespace();
+                if !checker.eat(b']') {
+                    containers.push(b'[');
+                    continue 'value;
+                }
+            }
+            Some(b'{') => {
+                checker.index += 1;
+                checker.skip_whitespace();
+                if !checker.eat(b'}') {
+                    if checker.peek() != Some(b'"') {
+                        return Err(checker.fail(Some(Reason::PropertyNameOrBrace)));
+                    }
+                    try!(checker.property_name());
+                    containers.push(b'{');
+                    continue 'value;
+                }
+            }
+            _ => return Err(checker.fail(None)),
+        }
+
+        // A value is complete; close the containers it completes.
+        loop {
+            checker.skip_whitespace();
+            match containers.last() {
+                None if checker.peek().is_none() => return Ok(()),
+                None => return Err(checker.fail(Some(Reason::NonWhitespaceAfterJson))),
+                Some(&b'[') => match checker.peek() {
+                    Some(b',') => {
+                        checker.index += 1;
+                        continue 'value;
+                    }
+                    Some(b']') => {
+                        checker.index += 1;
+                        containers.pop();
+                    }
+                    _ => return Err(checker.fail(Some(Reason::CommaOrBracketAfterElement))),
+                },
+                Some(_) => match checker.peek() {
+                    Some(b',') => {
+                        checker.index += 1;
+                        checker.skip_whitespace();
+                        if checker.peek() != Some(b'"') {
+                            return Err(checker.fail(Some(Reason::DoubleQuotedPropertyName)));
+                        }
+                        try!(checker.property_name());
+                        continue 'value;
+                    }
+                    Some(b'}') => {
+                        checker.index += 1;
+                        containers.pop();
+                    }
+                    _ => return Err(checker.fail(Some(Reason::CommaOrBraceAfterValue))),
+                },
+            }
+        }
+    }
+}
+
+struct Checker<'a> {
+    input: &'a [u8],
+    index: usize,
+}
+
+impl Checker<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.index).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_digits(&mut self) -> bool {
+        let start = self.index;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.index += 1;
+        }
+        self.index > start
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.index += 1;
+        }
+    }
+
+    fn fail(&self, reason: Option<Reason>) -> Failure {
+        Failure {
+            index: self.index,
+            reason: reason,
+        }
+    }
+
+    fn property_name(&mut self) -> Result<(), Failure> {
+        try!(self.string());
+        self.skip_whitespace();
+        if self.eat(b':') {
+            Ok(())
+        } else {
+            Err(self.fail(Some(Reason::ColonAfterPropertyName)))
+        }
+    }
+
+    fn string(&mut self) -> Result<(), Failure> {
+        self.index += 1;
+        loop {
+            match self.peek() {
+                None => return Err(self.fail(Some(Reason::UnterminatedString))),
+                Some(b'"') => {
+                    self.index += 1;
+                    return Ok(());
+                }
+                Some(b'\\') => {
+                    self.index += 1;
+                    match self.peek() {
+                        None => return Err(self.fail(Some(Reason::UnterminatedString))),
+                        Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => self.index += 1,
+                        Some(b'u') => {
+                            self.index += 1;
+                            for _ in 0..4 {
+                                match self.peek() {
+                                    Some(b) if b.is_ascii_hexdigit() => self.index += 1,
+                                    _ => return Err(self.fail(Some(Reason::BadUnicodeEscape))),
+                                }
+                            }
+                        }
+                        Some(_) => return Err(self.fail(Some(Reason::BadEscapedCharacter))),
+                    }
+                }
+                Some(b) if b < 0x20 => return Err(self.fail(Some(Reason::BadControlCharacter))),
+                Some(_) => self.index += 1,
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<(), Failure> {
+        if self.eat(b'-') && !matches!(self.peek(), Some(b'0'..=b'9')) {
+            return Err(self.fail(Some(Reason::NoNumberAfterMinusSign)));
+        }
+        if self.eat(b'0') {
+            if matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(self.fail(None));
+            }
+        } else {
+            self.eat_digits();
+        }
+        if self.eat(b'.') && !self.eat_digits() {
+            return Err(self.fail(Some(Reason::UnterminatedFractionalNumber)));
+        }
+        if self.eat(b'e') || self.eat(b'E') {
+            if !self.eat(b'+') {
+                self.eat(b'-');
+            }
+            if !self.eat_digits() {
+                return Err(self.fail(Some(Reason::ExponentPartMissingNumber)));
+            }
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, literal: &[u8]) -> Result<(), Failure> {
+        for &expected in literal {
+            if self.peek() != Some(expected) {
+                return Err(self.fail(None));
+            }
+            self.index += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Translates a failure into the terms of V8's messages: the unexpected
+/// token, UTF-16 positions and the quoted context.
+fn locate(input: &str, failure: Failure) -> Syntax {
+    let rest = &input[failure.index..];
+    let token = match rest.chars().next() {
+        None => Token::End,
+        Some('"') => Token::String,
+        Some(c) if c == '-' || c.is_ascii_digit() => Token::Number,
+        Some(c) => Token::Char(c),
+    };
+
+    let units: Vec<u16> = input.encode_utf16().collect();
+    let position = input[..failure.index].encode_utf16().count();
+
+    // V8 counts `\r`, `\n` and `\r\n` as line breaks.
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut i = 0;
+    while i < position {
+        if units[i] == u16::from(b'\r') && i + 1 < position && units[i + 1] == u16::from(b'\n') {
+            i += 1;
+        }
+        if units[i] == u16::from(b'\r') || units[i] == u16::from(b'\n') {
+            line += 1;
+            line_start = i + 1;
+        }
+        i += 1;
+    }
+
+    let len = units.len();
+    let quote = |start: usize, end: usize| String::from_utf16_lossy(&units[start..cmp::min(end, len)]);
+    let context = if len <= 2 * MAX_CONTEXT {
+        Context::Whole(input.to_owned())
+    } else if position < MAX_CONTEXT {
+        Context::Start(quote(0, position + MAX_CONTEXT))
+    } else if position < len - MAX_CONTEXT {
+        Context::Middle(quote(position - MAX_CONTEXT, position + MAX_CONTEXT))
+    } else {
+        Context::End(quote(position - MAX_CONTEXT, len))
+    };
+
+    Syntax {
+        token: token,
+        reason: failure.reason,
+        position: position,
+        line: line,
+        column: position - line_start + 1,
+        context: context,
+    }
+}
@@ -0,0 +1,112 @@
+extern crate serde;
+extern crate serde_json_v8;
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json_v8::digest::Digest;
+use serde_json_v8::ser::ErrorKind;
+use serde_json_v8::RawJson;
+
+/// Serializes like serde_json's `RawValue`, which may hold any JSON text.
+struct RawValue(&'static str);
+
+impl Serialize for RawValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        const TOKEN: &str = "$serde_json::private::RawValue";
+        let mut s = serializer.serialize_struct(TOKEN, 1)?;
+        s.serialize_field(TOKEN, self.0)?;
+        s.end()
+    }
+}
+
+struct Collect(Vec<u8>);
+
+impl Digest for Collect {
+    type Output = Vec<u8>;
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.extend_from_slice(data);
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+fn raw(text: &str) -> RawJson {
+    RawJson::new(text).unwrap()
+}
+
+#[test]
+fn test_raw_json_numbers() {
+    let value = vec![raw("1.50"), raw("-0"), raw("1E3"), raw("123456789012345678901")];
+    assert_eq!(
+        serde_json_v8::to_canonical_string(&value).unwrap(),
+        "[1.5,0,1000,123456789012345680000]"
+    );
+}
+
+#[test]
+fn test_raw_json_strings() {
+    let value = vec![raw(r#""\u0041\/""#), raw(r#""é""#), raw("true"), raw("null")];
+    assert_eq!(
+        serde_json_v8::to_canonical_string(&value).unwrap(),
+        "[\"A/\",\"\u{e9}\",true,null]"
+    );
+}
+
+#[test]
+fn test_raw_value_containers() {
+    let value = vec![RawValue(r#"{ "b": [1.0, "A"], "a": {"é": 1e2, "€": 0} }"#)];
+    assert_eq!(
+        serde_json_v8::to_canonical_string(&value).unwrap(),
+        "[{\"a\":{\"\u{e9}\":100,\"\u{20ac}\":0},\"b\":[1,\"A\"]}]"
+    );
+}
+
+#[test]
+fn test_raw_json_not_finite() {
+    let err = serde_json_v8::to_canonical_string(&vec![raw("1e400")]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NonFiniteNumber);
+    assert_eq!(err.to_string(), "1e400 is not a finite number at $[0]");
+
+    let err = serde_json_v8::to_canonical_string(&RawValue(r#"{"a": [1e400]}"#)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NonFiniteNumber);
+}
+
+#[test]
+fn test_lone_surrogates() {
+    let value = vec![raw(r#""\ud800""#), raw(r#""a\udc00\ud83d\ude00""#)];
+    assert_eq!(
+        serde_json_v8::to_canonical_string(&value).unwrap(),
+        "[\"\\ud800\",\"a\\udc00\u{1f600}\"]"
+    );
+
+    // Names sort by their UTF-16 code units, lone surrogates included.
+    let value = RawValue(r#"{"\uffff": 1, "\ud800": ["\udfff"], "\ud83d\ude00": 2}"#);
+    assert_eq!(
+        serde_json_v8::to_canonical_string(&value).unwrap(),
+        "{\"\\ud800\":[\"\\udfff\"],\"\u{1f600}\":2,\"\u{ffff}\":1}"
+    );
+}
+
+#[test]
+fn test_raw_value_duplicates_and_invalid() {
+    let value = RawValue(r#"{"b": 1, "a": 2, "b": 3}"#);
+    assert_eq!(serde_json_v8::to_canonical_string(&value).unwrap(), r#"{"a":2,"b":3}"#);
+
+    for text in &["[1,]", "{\"a\" 1}", "[1] 2", "tru", "{1: 2}"] {
+        let err = serde_json_v8::to_canonical_string(&RawValue(text)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Custom, "{}", text);
+    }
+}
+
+#[test]
+fn test_digest_matches_vec() {
+    let value = vec![raw("1.50"), raw(r#""A""#)];
+    let digest = serde_json_v8::to_canonical_digest(Collect(Vec::new()), &value).unwrap();
+    assert_eq!(digest, br#"[1.5,"A"]"#);
+    assert_eq!(digest, serde_json_v8::to_canonical_vec(&value).unwrap());
+}
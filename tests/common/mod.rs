@@ -0,0 +1,15 @@
+use serde::ser::Serialize;
+use serde_json_v8::{Result, Serializer};
+
+/// Serializes `value` with a compact `Serializer` whose options are set by
+/// `configure`.
+pub fn to_string_with<T, F>(value: &T, configure: F) -> Result<String>
+where
+    T: ?Sized + Serialize,
+    F: FnOnce(&mut Serializer<Vec<u8>>),
+{
+    let mut ser = Serializer::new(Vec::new());
+    configure(&mut ser);
+    value.serialize(&mut ser)?;
+    Ok(String::from_utf8(ser.into_inner()).unwrap())
+}
@@ -0,0 +1,92 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use serde::ser::Serialize;
+use serde_json_v8::count::CountingWriter;
+use serde_json_v8::ser::{KeyOrder, Serializer};
+
+#[derive(Serialize)]
+struct Record {
+    zeta: f64,
+    #[serde(rename = "2")]
+    two: &'static str,
+    #[serde(rename = "1")]
+    one: Vec<Option<u64>>,
+}
+
+fn record() -> Record {
+    Record {
+        zeta: -0.0,
+        two: "<é😀>",
+        one: vec![Some(u64::MAX), None],
+    }
+}
+
+fn values() -> Vec<serde_json::Value> {
+    vec![
+        json!(null),
+        json!([]),
+        json!({}),
+        json!(1e21),
+        json!("\u{0}\n\"\\\u{7f}\u{2028}é😀"),
+        json!({"b": [0.1, -0.0, 1e-7, [], {}], "a": {"c": "😀😀"}}),
+        serde_json::to_value(record()).unwrap(),
+    ]
+}
+
+#[test]
+fn test_serialized_len() {
+    for value in values() {
+        let json = serde_json_v8::to_string(&value).unwrap();
+        assert_eq!(serde_json_v8::serialized_len(&value).unwrap(), json.len());
+        assert_eq!(
+            serde_json_v8::serialized_utf16_len(&value).unwrap(),
+            json.encode_utf16().count()
+        );
+    }
+    let json = serde_json_v8::to_string(&record()).unwrap();
+    assert_eq!(serde_json_v8::serialized_len(&record()).unwrap(), json.len());
+}
+
+#[test]
+fn test_serialized_len_pretty() {
+    for value in values() {
+        let json = serde_json_v8::to_string_pretty(&value).unwrap();
+        assert_eq!(serde_json_v8::serialized_len_pretty(&value).unwrap(), json.len());
+        assert_eq!(
+            serde_json_v8::serialized_utf16_len_pretty(&value).unwrap(),
+            json.encode_utf16().count()
+        );
+    }
+}
+
+#[test]
+fn test_counting_writer_with_settings() {
+    let mut ser = Serializer::pretty(Vec::new());
+    ser.set_key_order(KeyOrder::V8);
+    ser.set_ensure_ascii(true);
+    ser.set_html_safe(true);
+    record().serialize(&mut ser).unwrap();
+    let json = String::from_utf8(ser.into_inner()).unwrap();
+
+    let mut counter = CountingWriter::new();
+    let mut ser = Serializer::pretty(&mut counter);
+    ser.set_key_order(KeyOrder::V8);
+    ser.set_ensure_ascii(true);
+    ser.set_html_safe(true);
+    record().serialize(&mut ser).unwrap();
+    assert_eq!(counter.bytes(), json.len());
+    assert_eq!(counter.utf16_len(), json.len());
+}
+
+#[test]
+fn test_counting_writer_surrogate_pairs() {
+    let mut counter = CountingWriter::new();
+    serde_json_v8::to_writer(&mut counter, "a😀é").unwrap();
+    assert_eq!(counter.bytes(), 2 + 1 + 4 + 2);
+    assert_eq!(counter.utf16_len(), 2 + 1 + 2 + 1);
+}
@@ -0,0 +1,122 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use serde::ser::Serialize;
+use serde_json_v8::digest::{Digest, DigestWriter};
+use serde_json_v8::ser::{KeyOrder, NumberPolicy, PrettyV8Formatter, Serializer};
+use serde_json_v8::JsString;
+
+/// Keeps the input, so that digests can be compared with serialized output.
+struct Collect(Vec<u8>);
+
+impl Digest for Collect {
+    type Output = Vec<u8>;
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.extend_from_slice(data);
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+/// 64-bit FNV-1a, which does not depend on how its input is split.
+struct Fnv(u64);
+
+impl Digest for Fnv {
+    type Output = u64;
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finalize(self) -> u64 {
+        self.0
+    }
+}
+
+fn fnv(data: &[u8]) -> u64 {
+    let mut digest = Fnv(0xcbf29ce484222325);
+    digest.update(data);
+    digest.finalize()
+}
+
+#[derive(Serialize)]
+struct Record {
+    zeta: f64,
+    #[serde(rename = "1")]
+    one: JsString,
+    alpha: Vec<Option<u64>>,
+}
+
+fn record() -> Record {
+    Record {
+        zeta: -0.0,
+        one: JsString::from(vec![0x3c, 0xd800, 0x22]),
+        alpha: vec![Some(u64::MAX), None],
+    }
+}
+
+fn values() -> Vec<serde_json::Value> {
+    vec![
+        json!(null),
+        json!(1e21),
+        json!("\u{0}\n\"\\\u{7f}\u{2028}é😀"),
+        json!({"b": [0.1, -0.0, 1e-7], "a": {"c": []}}),
+        serde_json::to_value(record()).unwrap(),
+    ]
+}
+
+#[test]
+fn test_to_digest_matches_to_vec() {
+    for value in values() {
+        let json = serde_json_v8::to_vec(&value).unwrap();
+        assert_eq!(serde_json_v8::to_digest(Collect(Vec::new()), &value).unwrap(), json);
+        assert_eq!(
+            serde_json_v8::to_digest(Fnv(0xcbf29ce484222325), &value).unwrap(),
+            fnv(&json)
+        );
+    }
+    let json = serde_json_v8::to_vec(&record()).unwrap();
+    assert_eq!(serde_json_v8::to_digest(Collect(Vec::new()), &record()).unwrap(), json);
+}
+
+#[test]
+fn test_to_canonical_digest_matches_to_canonical_vec() {
+    for value in values() {
+        let json = serde_json_v8::to_canonical_vec(&value).unwrap();
+        assert_eq!(
+            serde_json_v8::to_canonical_digest(Collect(Vec::new()), &value).unwrap(),
+            json
+        );
+    }
+}
+
+#[test]
+fn test_digest_writer_with_settings() {
+    let mut ser = Serializer::with_formatter(Vec::new(), PrettyV8Formatter::new());
+    ser.set_key_order(KeyOrder::V8);
+    ser.set_number_policy(NumberPolicy::Preserve);
+    ser.set_html_safe(true);
+    (record(), f64::NAN).serialize(&mut ser).unwrap();
+    let json = ser.into_inner();
+
+    let mut ser = Serializer::with_formatter(DigestWriter::new(Collect(Vec::new())), PrettyV8Formatter::new());
+    ser.set_key_order(KeyOrder::V8);
+    ser.set_number_policy(NumberPolicy::Preserve);
+    ser.set_html_safe(true);
+    (record(), f64::NAN).serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_inner().finalize(), json);
+}
+
+#[test]
+fn test_errors() {
+    assert!(serde_json_v8::to_canonical_digest(Collect(Vec::new()), &f64::NAN).is_err());
+}
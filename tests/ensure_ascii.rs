@@ -0,0 +1,54 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_json_v8;
+
+mod common;
+
+use common::to_string_with;
+use serde_json_v8::{JsString, RawJson};
+
+#[test]
+fn test_strings_and_keys() {
+    let value = json!({"clé": ["café", "😀", "\n\"\\"]});
+    assert_eq!(
+        to_string_with(&value, |ser| ser.set_ensure_ascii(true)).unwrap(),
+        r#"{"cl\u00e9":["caf\u00e9","\ud83d\ude00","\n\"\\"]}"#
+    );
+    // DEL is ASCII.
+    assert_eq!(
+        to_string_with("\u{7f}\u{80}", |ser| ser.set_ensure_ascii(true)).unwrap(),
+        "\"\u{7f}\\u0080\""
+    );
+}
+
+#[test]
+fn test_round_trip() {
+    let value = json!(["é€😀\u{10ffff}\u{ffff}"]);
+    let ascii = to_string_with(&value, |ser| ser.set_ensure_ascii(true)).unwrap();
+    assert!(ascii.is_ascii());
+    // JSON.parse(ascii)
+    assert_eq!(serde_json_v8::from_str::<serde_json::Value>(&ascii).unwrap(), value);
+}
+
+#[test]
+fn test_js_string() {
+    let value = JsString::from(vec![0xe9, 0xd800, 0x61, 0xd83d, 0xde00, 0xdc00]);
+    assert_eq!(
+        serde_json_v8::to_string(&value).unwrap(),
+        "\"\u{e9}\\ud800a\u{1f600}\\udc00\""
+    );
+    let ascii = to_string_with(&value, |ser| ser.set_ensure_ascii(true)).unwrap();
+    assert_eq!(ascii, r#""\u00e9\ud800a\ud83d\ude00\udc00""#);
+    let parsed: JsString = serde_json_v8::de::from_str_v8(&ascii).unwrap();
+    assert_eq!(parsed, value);
+}
+
+#[test]
+fn test_raw_json() {
+    let value = vec![RawJson::new("\"caf\u{e9}\"").unwrap(), RawJson::new("1.50").unwrap()];
+    assert_eq!(
+        to_string_with(&value, |ser| ser.set_ensure_ascii(true)).unwrap(),
+        r#"["caf\u00e9",1.50]"#
+    );
+}
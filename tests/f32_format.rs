@@ -0,0 +1,40 @@
+extern crate serde;
+extern crate serde_json_v8;
+
+mod common;
+
+use common::to_string_with;
+use serde_json_v8::ser::F32Format;
+
+const VALUES: &[f32] = &[0.1, f32::MAX, 1e-45, 16777216.0, 1e21, 1e20, 3.3, -0.0];
+
+#[test]
+fn test_widened() {
+    // JSON.stringify(Array.from(new Float32Array([0.1, 3.4028235e38, 1e-45, 16777216, 1e21, 1e20, 3.3, -0])))
+    assert_eq!(
+        to_string_with(VALUES, |ser| ser.set_f32_format(F32Format::Widened)).unwrap(),
+        "[0.10000000149011612,3.4028234663852886e+38,1.401298464324817e-45,16777216,1.0000000200408773e+21,100000002004087730000,3.299999952316284,0]"
+    );
+    assert_eq!(
+        serde_json_v8::to_string(VALUES).unwrap(),
+        to_string_with(VALUES, |ser| ser.set_f32_format(F32Format::Widened)).unwrap()
+    );
+}
+
+#[test]
+fn test_shortest() {
+    assert_eq!(
+        to_string_with(VALUES, |ser| ser.set_f32_format(F32Format::Shortest)).unwrap(),
+        "[0.1,3.4028235e+38,1e-45,16777216,1e+21,100000000000000000000,3.3,0]"
+    );
+}
+
+#[test]
+fn test_shortest_round_trips_through_fround() {
+    for &value in &[0.1f32, 1.0 / 3.0, 123456.79, 1e-10, f32::MIN_POSITIVE, f32::EPSILON] {
+        let text = to_string_with(&[value], |ser| ser.set_f32_format(F32Format::Shortest)).unwrap();
+        let parsed: f64 = text.trim_matches(|c| c == '[' || c == ']').parse().unwrap();
+        // Math.fround(Number(text)) === value
+        assert_eq!(parsed as f32, value, "{}", text);
+    }
+}
@@ -0,0 +1,50 @@
+extern crate serde;
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use serde_json::Value;
+use serde_json_v8::JsString;
+
+fn utf16(s: &str) -> Vec<u16> {
+    s.encode_utf16().collect()
+}
+
+#[test]
+fn test_escaped_lone_surrogate() {
+    // JSON.parse('"\\ud800"')
+    let s: JsString = serde_json_v8::from_utf16(&utf16(r#""\ud800""#)).unwrap();
+    assert_eq!(s.as_utf16(), &[0xd800]);
+
+    let s: JsString = serde_json_v8::from_utf16(&utf16(r#""a\udc00😀""#)).unwrap();
+    assert_eq!(s.as_utf16(), &[0x61, 0xdc00, 0xd83d, 0xde00]);
+}
+
+#[test]
+fn test_raw_lone_surrogate() {
+    // JSON.parse('"\ud800"')
+    let s: JsString = serde_json_v8::from_utf16(&[0x22, 0xd800, 0x22]).unwrap();
+    assert_eq!(s.as_utf16(), &[0xd800]);
+
+    // JSON.parse('["\\\\\udc00"]')
+    let v: Vec<JsString> = serde_json_v8::from_utf16(&[0x5b, 0x22, 0x5c, 0x5c, 0xdc00, 0x22, 0x5d]).unwrap();
+    assert_eq!(v[0].as_utf16(), &[0x5c, 0xdc00]);
+}
+
+#[test]
+fn test_invalid_lone_surrogate() {
+    // After a backslash.
+    let result: Result<JsString, _> = serde_json_v8::from_utf16(&[0x22, 0x5c, 0xd800, 0x22]);
+    assert!(result.is_err());
+    // Outside of a string.
+    let result: Result<Value, _> = serde_json_v8::from_utf16(&[0x5b, 0xd800, 0x5d]);
+    assert!(result.is_err());
+    // In a string deserialized as something else than a `JsString`.
+    let result: Result<String, _> = serde_json_v8::from_utf16(&[0x22, 0xd800, 0x22]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_value() {
+    let value: Value = serde_json_v8::from_utf16(&utf16(r#"{"a": ["😀", 1.5]}"#)).unwrap();
+    assert_eq!(value, serde_json::json!({"a": ["\u{1f600}", 1.5]}));
+}
@@ -0,0 +1,61 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_json_v8;
+
+mod common;
+
+use common::to_string_with;
+use serde_json_v8::{JsString, RawJson};
+
+#[test]
+fn test_strings_and_keys() {
+    let value = json!({"<a>": ["</script>", "a & b", "\u{2028}\u{2029}", "é/"]});
+    assert_eq!(
+        serde_json_v8::to_string_html_safe(&value).unwrap(),
+        "{\"\\u003ca\\u003e\":[\"\\u003c/script\\u003e\",\"a \\u0026 b\",\"\\u2028\\u2029\",\"\u{e9}/\"]}"
+    );
+    assert_eq!(
+        to_string_with(&value, |ser| ser.set_html_safe(true)).unwrap(),
+        serde_json_v8::to_string_html_safe(&value).unwrap()
+    );
+}
+
+#[test]
+fn test_with_ensure_ascii() {
+    let value = json!(["<é>", "\u{2028}😀"]);
+    assert_eq!(
+        to_string_with(&value, |ser| {
+            ser.set_html_safe(true);
+            ser.set_ensure_ascii(true);
+        })
+        .unwrap(),
+        r#"["\u003c\u00e9\u003e","\u2028\ud83d\ude00"]"#
+    );
+}
+
+#[test]
+fn test_js_string() {
+    let value = JsString::from(vec![0x3c, 0xd800, 0x26, 0xe9, 0x2028, 0x3e]);
+    assert_eq!(
+        to_string_with(&value, |ser| ser.set_html_safe(true)).unwrap(),
+        "\"\\u003c\\ud800\\u0026\u{e9}\\u2028\\u003e\""
+    );
+    let ascii = to_string_with(&value, |ser| {
+        ser.set_html_safe(true);
+        ser.set_ensure_ascii(true);
+    })
+    .unwrap();
+    assert_eq!(ascii, r#""\u003c\ud800\u0026\u00e9\u2028\u003e""#);
+    let parsed: JsString = serde_json_v8::de::from_str_v8(&ascii).unwrap();
+    assert_eq!(parsed, value);
+}
+
+#[test]
+fn test_raw_json() {
+    let value = vec![RawJson::new(r#""</script>""#).unwrap()];
+    assert_eq!(
+        to_string_with(&value, |ser| ser.set_html_safe(true)).unwrap(),
+        r#"["\u003c/script\u003e"]"#
+    );
+}
@@ -0,0 +1,97 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_json_v8;
+
+mod common;
+
+use common::to_string_with;
+use serde_json_v8::ser::{ErrorKind, IntegerPolicy};
+
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+fn boundaries() -> (i64, u64, i64, i64) {
+    (MAX_SAFE_INTEGER, 1 << 53, -MAX_SAFE_INTEGER, -(1 << 53))
+}
+
+fn extremes() -> (u64, i64, u128, i128) {
+    (u64::MAX, i64::MIN, u128::MAX, i128::MIN)
+}
+
+#[test]
+fn test_exact() {
+    assert_eq!(
+        to_string_with(&boundaries(), |ser| ser.set_integer_policy(IntegerPolicy::Exact)).unwrap(),
+        "[9007199254740991,9007199254740992,-9007199254740991,-9007199254740992]"
+    );
+    assert_eq!(
+        to_string_with(&extremes(), |ser| ser.set_integer_policy(IntegerPolicy::Exact)).unwrap(),
+        "[18446744073709551615,-9223372036854775808,340282366920938463463374607431768211455,-170141183460469231731687303715884105728]"
+    );
+}
+
+#[test]
+fn test_round_like_v8() {
+    // [2n**64n-1n, -(2n**63n), 2n**128n-1n, -(2n**127n)].map(Number)
+    assert_eq!(
+        to_string_with(&boundaries(), |ser| {
+            ser.set_integer_policy(IntegerPolicy::RoundLikeV8)
+        })
+        .unwrap(),
+        "[9007199254740991,9007199254740992,-9007199254740991,-9007199254740992]"
+    );
+    assert_eq!(
+        to_string_with(&extremes(), |ser| {
+            ser.set_integer_policy(IntegerPolicy::RoundLikeV8)
+        })
+        .unwrap(),
+        "[18446744073709552000,-9223372036854776000,3.402823669209385e+38,-1.7014118346046923e+38]"
+    );
+}
+
+#[test]
+fn test_stringify_unsafe() {
+    assert_eq!(
+        to_string_with(&boundaries(), |ser| {
+            ser.set_integer_policy(IntegerPolicy::StringifyUnsafe)
+        })
+        .unwrap(),
+        r#"[9007199254740991,"9007199254740992",-9007199254740991,"-9007199254740992"]"#
+    );
+    assert_eq!(
+        to_string_with(&json!({"id": u64::MAX}), |ser| {
+            ser.set_integer_policy(IntegerPolicy::StringifyUnsafe)
+        })
+        .unwrap(),
+        r#"{"id":"18446744073709551615"}"#
+    );
+}
+
+#[test]
+fn test_error() {
+    assert_eq!(
+        to_string_with(&(MAX_SAFE_INTEGER, -MAX_SAFE_INTEGER), |ser| {
+            ser.set_integer_policy(IntegerPolicy::Error)
+        })
+        .unwrap(),
+        "[9007199254740991,-9007199254740991]"
+    );
+
+    let err = to_string_with(&json!({"ids": [1, -(1i64 << 53)]}), |ser| {
+        ser.set_integer_policy(IntegerPolicy::Error)
+    })
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnsafeInteger);
+    assert_eq!(err.path(), Some("$.ids[1]"));
+    assert_eq!(
+        err.to_string(),
+        "-9007199254740992 is outside of the safe integer range at $.ids[1]"
+    );
+
+    let err = to_string_with(&u128::MAX, |ser| {
+        ser.set_integer_policy(IntegerPolicy::Error)
+    })
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnsafeInteger);
+    assert_eq!(err.path(), None);
+}
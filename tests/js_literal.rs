@@ -0,0 +1,49 @@
+extern crate serde;
+extern crate serde_json;
+extern crate serde_json_v8;
+
+mod common;
+
+use common::to_string_with;
+use serde::Serialize;
+use serde_json_v8::ser::{IntegerPolicy, JsLiteralFormatter, Serializer};
+use serde_json_v8::RawJson;
+
+fn to_js_literal<T>(value: &T, number_literals: bool) -> String
+where
+    T: Serialize,
+{
+    let mut formatter = JsLiteralFormatter::with_indent(b"");
+    formatter.set_number_literals(number_literals);
+    let mut ser = Serializer::js_literal(Vec::new(), formatter);
+    value.serialize(&mut ser).unwrap();
+    String::from_utf8(ser.into_inner()).unwrap()
+}
+
+#[test]
+fn test_bigint() {
+    let value = (u64::MAX, i128::MIN, 9007199254740991u64, 10, -0.0, f64::NAN);
+    assert_eq!(
+        to_js_literal(&value, true),
+        "[18446744073709551615n,-170141183460469231731687303715884105728n,9007199254740991,10,-0,NaN]"
+    );
+    assert_eq!(
+        to_js_literal(&value, false),
+        "[18446744073709551615,-170141183460469231731687303715884105728,9007199254740991,10,0,null]"
+    );
+}
+
+#[test]
+fn test_raw_json_is_not_bigint() {
+    let value = vec![RawJson::new("12345678901234567890").unwrap(), RawJson::new("-10").unwrap()];
+    assert_eq!(to_js_literal(&value, true), "[12345678901234567890,-10]");
+}
+
+#[test]
+fn test_bigint_policy() {
+    let value = (u64::MAX, 1u64 << 53, -(1i64 << 53));
+    assert_eq!(
+        to_string_with(&value, |ser| ser.set_integer_policy(IntegerPolicy::BigInt)).unwrap(),
+        "[18446744073709551615n,9007199254740992n,-9007199254740992n]"
+    );
+}
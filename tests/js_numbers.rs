@@ -0,0 +1,92 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use serde::de::Deserialize;
+use serde_json::Value;
+use serde_json_v8::de::V8Deserializer;
+
+fn from_str_js<'a, T>(s: &'a str) -> serde_json::Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = V8Deserializer::from_str(s);
+    de.set_js_numbers(true);
+    let value = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(value)
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Ids {
+    unsigned: u64,
+    signed: i64,
+}
+
+#[test]
+fn test_round_trip() {
+    // JSON.stringify(JSON.parse(text))
+    let text = "[1e400,-1e400,-0,-0.0,0,12345678901234567891,9007199254740993,\
+                -9007199254740993,9007199254740991,1e21,1.0,1e-400,-1e-400,0.1]";
+    let value: Value = from_str_js(text).unwrap();
+    assert_eq!(
+        serde_json_v8::to_string(&value).unwrap(),
+        "[null,null,0,0,0,12345678901234567000,9007199254740992,-9007199254740992,\
+         9007199254740991,1e+21,1,0,0,0.1]"
+    );
+}
+
+#[test]
+fn test_large_integers_round() {
+    let value: Value = from_str_js("[12345678901234567891, 9007199254740993]").unwrap();
+    assert_eq!(value[0].as_u64(), None);
+    assert_eq!(value[0].as_f64(), Some(12345678901234567891.0));
+    assert_eq!(value[1].as_u64(), None);
+    assert_eq!(value[1].as_f64(), Some(9007199254740992.0));
+
+    // Without the mode the integers are kept exactly.
+    let mut de = V8Deserializer::from_str("12345678901234567891");
+    assert_eq!(u64::deserialize(&mut de).unwrap(), 12345678901234567891);
+}
+
+#[test]
+fn test_negative_zero() {
+    let value: Value = from_str_js("[-0, -0.0, 0, -1e-400]").unwrap();
+    let signs: Vec<_> = value
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|number| number.as_f64().unwrap().is_sign_negative())
+        .collect();
+    assert_eq!(signs, [true, true, false, true]);
+    assert!(from_str_js::<f64>("-0").unwrap().is_sign_negative());
+}
+
+#[test]
+fn test_infinity() {
+    assert_eq!(from_str_js::<f64>("1e400").unwrap(), f64::INFINITY);
+    assert_eq!(from_str_js::<f64>("-1e400").unwrap(), f64::NEG_INFINITY);
+
+    // Without the mode the number is out of range.
+    let mut de = V8Deserializer::from_str("1e400");
+    let err = f64::deserialize(&mut de).unwrap_err();
+    assert_eq!(err.to_string(), "number out of range at line 1 column 6");
+}
+
+#[test]
+fn test_integer_fields() {
+    assert_eq!(
+        from_str_js::<Ids>(r#"{"unsigned":1e3,"signed":-9007199254740991}"#).unwrap(),
+        Ids {
+            unsigned: 1000,
+            signed: -9_007_199_254_740_991,
+        }
+    );
+
+    // Integers beyond the safe range are doubles, and `-0` is not an integer.
+    assert!(from_str_js::<Ids>(r#"{"unsigned":9007199254740992,"signed":0}"#).is_err());
+    assert!(from_str_js::<Ids>(r#"{"unsigned":0,"signed":-0}"#).is_err());
+    assert!(from_str_js::<Ids>(r#"{"unsigned":1.5,"signed":0}"#).is_err());
+}
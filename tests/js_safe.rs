@@ -0,0 +1,161 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_json_v8;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Wide {
+    #[serde(with = "serde_json_v8::js_safe")]
+    unsigned: u128,
+    #[serde(with = "serde_json_v8::js_safe")]
+    signed: i128,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Narrow {
+    #[serde(with = "serde_json_v8::js_safe")]
+    unsigned: u64,
+    #[serde(with = "serde_json_v8::js_safe")]
+    signed: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Nested {
+    #[serde(with = "serde_json_v8::js_safe")]
+    ids: Vec<Option<u64>>,
+    #[serde(with = "serde_json_v8::js_safe")]
+    parent: Option<Vec<i128>>,
+}
+
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+fn round_trip<T>(value: &T, json: &str)
+where
+    T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    assert_eq!(serde_json_v8::to_string(value).unwrap(), json);
+    assert_eq!(&serde_json_v8::from_str::<T>(json).unwrap(), value);
+}
+
+#[test]
+fn test_safe_range_bounds() {
+    round_trip(
+        &Narrow {
+            unsigned: MAX_SAFE_INTEGER as u64,
+            signed: -MAX_SAFE_INTEGER,
+        },
+        r#"{"unsigned":9007199254740991,"signed":-9007199254740991}"#,
+    );
+    round_trip(
+        &Narrow {
+            unsigned: 1 << 53,
+            signed: -(1 << 53),
+        },
+        r#"{"unsigned":"9007199254740992","signed":"-9007199254740992"}"#,
+    );
+    round_trip(
+        &Wide {
+            unsigned: MAX_SAFE_INTEGER as u128,
+            signed: -i128::from(MAX_SAFE_INTEGER),
+        },
+        r#"{"unsigned":9007199254740991,"signed":-9007199254740991}"#,
+    );
+    round_trip(
+        &Wide {
+            unsigned: 1 << 53,
+            signed: -(1 << 53),
+        },
+        r#"{"unsigned":"9007199254740992","signed":"-9007199254740992"}"#,
+    );
+}
+
+#[test]
+fn test_extremes() {
+    round_trip(
+        &Narrow {
+            unsigned: u64::MAX,
+            signed: i64::MIN,
+        },
+        r#"{"unsigned":"18446744073709551615","signed":"-9223372036854775808"}"#,
+    );
+    round_trip(
+        &Wide {
+            unsigned: u128::MAX,
+            signed: i128::MIN,
+        },
+        r#"{"unsigned":"340282366920938463463374607431768211455","signed":"-170141183460469231731687303715884105728"}"#,
+    );
+}
+
+#[test]
+fn test_option_and_vec() {
+    round_trip(
+        &Nested {
+            ids: vec![Some(1), None, Some(u64::MAX)],
+            parent: Some(vec![-1, i128::MAX]),
+        },
+        r#"{"ids":[1,null,"18446744073709551615"],"parent":[-1,"170141183460469231731687303715884105727"]}"#,
+    );
+    round_trip(
+        &Nested {
+            ids: Vec::new(),
+            parent: None,
+        },
+        r#"{"ids":[],"parent":null}"#,
+    );
+}
+
+#[test]
+fn test_accepted_forms() {
+    // Safe integers may come as strings, and numbers written like `1e3` are
+    // integers too.
+    assert_eq!(
+        serde_json_v8::from_str::<Narrow>(r#"{"unsigned":"7","signed":1e3}"#).unwrap(),
+        Narrow {
+            unsigned: 7,
+            signed: 1000,
+        }
+    );
+}
+
+#[test]
+fn test_rejects_non_integers() {
+    for json in &[
+        r#"{"unsigned":"1.5","signed":0}"#,
+        r#"{"unsigned":"","signed":0}"#,
+        r#"{"unsigned":"-","signed":0}"#,
+        r#"{"unsigned":"+1","signed":0}"#,
+        r#"{"unsigned":" 1","signed":0}"#,
+        r#"{"unsigned":"1e3","signed":0}"#,
+        r#"{"unsigned":1.5,"signed":0}"#,
+        r#"{"unsigned":true,"signed":0}"#,
+    ] {
+        assert!(serde_json_v8::from_str::<Narrow>(json).is_err(), "{}", json);
+    }
+    let err = serde_json_v8::from_str::<Narrow>(r#"{"unsigned":"1.5","signed":0}"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid value: string \"1.5\", expected an integer or a string of digits at line 1 column 17"
+    );
+}
+
+#[test]
+fn test_out_of_range() {
+    let err = serde_json_v8::from_str::<Narrow>(r#"{"unsigned":-1,"signed":0}"#).unwrap_err();
+    assert_eq!(err.to_string(), "integer -1 is out of range at line 1 column 14");
+
+    let err =
+        serde_json_v8::from_str::<Narrow>(r#"{"unsigned":0,"signed":9223372036854775808}"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "integer 9223372036854775808 is out of range at line 1 column 42"
+    );
+
+    // Strings beyond the range of the type do not parse.
+    assert!(serde_json_v8::from_str::<Narrow>(r#"{"unsigned":"18446744073709551616","signed":0}"#).is_err());
+    assert!(serde_json_v8::from_str::<Narrow>(r#"{"unsigned":"-1","signed":0}"#).is_err());
+
+    // Numbers beyond the safe range were already rounded as floats.
+    assert!(serde_json_v8::from_str::<Wide>(r#"{"unsigned":1e20,"signed":0}"#).is_err());
+}
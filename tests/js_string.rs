@@ -0,0 +1,34 @@
+extern crate serde;
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use serde_json_v8::ser::Replacer;
+use serde_json_v8::JsString;
+
+fn lone() -> JsString {
+    JsString::from(vec![0x61, 0xd83d])
+}
+
+#[test]
+fn test_v8_serializer() {
+    // JSON.stringify(["a\ud83d"])
+    assert_eq!(serde_json_v8::to_string(&vec![lone()]).unwrap(), r#"["a\ud83d"]"#);
+    let identity = Replacer::function(|_, value| Some(value));
+    assert_eq!(
+        serde_json_v8::to_string_with_replacer(&vec![lone()], identity).unwrap(),
+        Some(r#"["a\ud83d"]"#.to_owned())
+    );
+}
+
+#[test]
+fn test_other_serializer() {
+    assert_eq!(serde_json::to_string(&vec![lone()]).unwrap(), "[\"a\u{fffd}\"]");
+    assert_eq!(serde_json::to_value(lone()).unwrap(), "a\u{fffd}");
+}
+
+#[test]
+fn test_well_formed() {
+    let s = JsString::from("a😀");
+    assert_eq!(serde_json::to_string(&s).unwrap(), "\"a\u{1f600}\"");
+    assert_eq!(serde_json_v8::to_string(&s).unwrap(), "\"a\u{1f600}\"");
+}
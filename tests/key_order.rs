@@ -0,0 +1,122 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_json_v8;
+
+mod common;
+
+use common::to_string_with;
+use serde::ser::Serialize;
+use serde_json_v8::ser::{KeyOrder, PrettyV8Formatter, Serializer};
+
+#[derive(Serialize)]
+struct Inner {
+    z: u32,
+    #[serde(rename = "10")]
+    ten: u32,
+    #[serde(rename = "9")]
+    nine: u32,
+}
+
+#[derive(Serialize)]
+struct Outer {
+    b: u32,
+    #[serde(rename = "4294967295")]
+    not_index: u32,
+    #[serde(rename = "4294967294")]
+    max_index: u32,
+    #[serde(rename = "01")]
+    leading_zero: u32,
+    #[serde(rename = "1")]
+    one: u32,
+    a: Inner,
+    #[serde(rename = "-1")]
+    negative: u32,
+    #[serde(rename = "0")]
+    zero: u32,
+}
+
+fn outer() -> Outer {
+    Outer {
+        b: 1,
+        not_index: 2,
+        max_index: 3,
+        leading_zero: 4,
+        one: 5,
+        a: Inner { z: 1, ten: 2, nine: 3 },
+        negative: 6,
+        zero: 7,
+    }
+}
+
+#[test]
+fn test_v8() {
+    // JSON.stringify({b: 1, "4294967295": 2, "4294967294": 3, "01": 4, "1": 5, a: {z: 1, "10": 2, "9": 3}, "-1": 6, "0": 7})
+    assert_eq!(
+        to_string_with(&outer(), |ser| ser.set_key_order(KeyOrder::V8)).unwrap(),
+        r#"{"0":7,"1":5,"4294967294":3,"b":1,"4294967295":2,"01":4,"a":{"9":3,"10":2,"z":1},"-1":6}"#
+    );
+}
+
+#[test]
+fn test_v8_pretty() {
+    let mut ser = Serializer::with_formatter(Vec::new(), PrettyV8Formatter::new());
+    ser.set_key_order(KeyOrder::V8);
+    outer().serialize(&mut ser).unwrap();
+    // JSON.stringify(..., null, 2)
+    assert_eq!(
+        String::from_utf8(ser.into_inner()).unwrap(),
+        r#"{
+  "0": 7,
+  "1": 5,
+  "4294967294": 3,
+  "b": 1,
+  "4294967295": 2,
+  "01": 4,
+  "a": {
+    "9": 3,
+    "10": 2,
+    "z": 1
+  },
+  "-1": 6
+}"#
+    );
+}
+
+#[test]
+fn test_as_is() {
+    assert_eq!(
+        to_string_with(&outer(), |ser| ser.set_key_order(KeyOrder::AsIs)).unwrap(),
+        r#"{"b":1,"4294967295":2,"4294967294":3,"01":4,"1":5,"a":{"z":1,"10":2,"9":3},"-1":6,"0":7}"#
+    );
+}
+
+#[derive(Serialize)]
+struct Unicode {
+    #[serde(rename = "é")]
+    e_acute: u32,
+    #[serde(rename = "😀")]
+    emoji: u32,
+    #[serde(rename = "｡")]
+    halfwidth: u32,
+    a: u32,
+    #[serde(rename = "B")]
+    upper: u32,
+}
+
+#[test]
+fn test_sorted_by_utf16() {
+    let value = Unicode {
+        e_acute: 1,
+        emoji: 2,
+        halfwidth: 3,
+        a: 4,
+        upper: 5,
+    };
+    // A surrogate pair sorts before U+FF61 in UTF-16, after it in UTF-8.
+    assert_eq!(
+        to_string_with(&value, |ser| ser.set_key_order(KeyOrder::Sorted)).unwrap(),
+        "{\"B\":5,\"a\":4,\"\u{e9}\":1,\"\u{1f600}\":2,\"\u{ff61}\":3}"
+    );
+}
@@ -0,0 +1,168 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_json_v8;
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::to_string_with;
+use serde::ser::{Serialize, Serializer};
+use serde_json_v8::ser::{ErrorKind, KeyPolicy};
+
+/// A map with the single entry `key: 0`.
+struct Entry<K>(K);
+
+impl<K> Serialize for Entry<K>
+where
+    K: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(Some((&self.0, 0)))
+    }
+}
+
+fn with_key<K>(key: K, policy: KeyPolicy) -> serde_json_v8::Result<String>
+where
+    K: Serialize,
+{
+    to_string_with(&Entry(key), |ser| ser.set_key_policy(policy))
+}
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+struct Point {
+    x: u32,
+}
+
+#[derive(Serialize)]
+enum Shape {
+    Empty,
+    Circle(u32),
+}
+
+#[test]
+fn test_coerce_primitives() {
+    // JSON.stringify({[key]: 0})
+    assert_eq!(with_key(1.5, KeyPolicy::Coerce).unwrap(), r#"{"1.5":0}"#);
+    assert_eq!(with_key(1e21, KeyPolicy::Coerce).unwrap(), r#"{"1e+21":0}"#);
+    assert_eq!(with_key(-0.0, KeyPolicy::Coerce).unwrap(), r#"{"0":0}"#);
+    assert_eq!(with_key(1e-7, KeyPolicy::Coerce).unwrap(), r#"{"1e-7":0}"#);
+    assert_eq!(with_key(f64::NAN, KeyPolicy::Coerce).unwrap(), r#"{"NaN":0}"#);
+    assert_eq!(with_key(-7i8, KeyPolicy::Coerce).unwrap(), r#"{"-7":0}"#);
+    assert_eq!(with_key(true, KeyPolicy::Coerce).unwrap(), r#"{"true":0}"#);
+    assert_eq!(with_key('a', KeyPolicy::Coerce).unwrap(), r#"{"a":0}"#);
+    assert_eq!(with_key(None::<u32>, KeyPolicy::Coerce).unwrap(), r#"{"null":0}"#);
+    assert_eq!(with_key((), KeyPolicy::Coerce).unwrap(), r#"{"null":0}"#);
+    assert_eq!(with_key(Shape::Empty, KeyPolicy::Coerce).unwrap(), r#"{"Empty":0}"#);
+}
+
+#[test]
+fn test_coerce_sequences() {
+    // JSON.stringify({[key]: 0})
+    let nested = (1, (2, None::<u32>), 3);
+    assert_eq!(with_key(nested, KeyPolicy::Coerce).unwrap(), r#"{"1,2,,3":0}"#);
+    assert_eq!(with_key(vec![()], KeyPolicy::Coerce).unwrap(), r#"{"":0}"#);
+    assert_eq!(with_key(Vec::<u32>::new(), KeyPolicy::Coerce).unwrap(), r#"{"":0}"#);
+}
+
+#[test]
+fn test_coerce_rejects_objects() {
+    // Each of these would silently be written as "[object Object]".
+    let err = with_key(Point { x: 1 }, KeyPolicy::Coerce).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::KeyMustBeAString);
+    assert_eq!(
+        with_key(BTreeMap::<u32, u32>::new(), KeyPolicy::Coerce).unwrap_err().kind(),
+        ErrorKind::KeyMustBeAString
+    );
+    assert_eq!(
+        with_key(Shape::Circle(1), KeyPolicy::Coerce).unwrap_err().kind(),
+        ErrorKind::KeyMustBeAString
+    );
+    assert_eq!(
+        with_key(vec![Point { x: 1 }], KeyPolicy::Coerce).unwrap_err().kind(),
+        ErrorKind::KeyMustBeAString
+    );
+}
+
+#[test]
+fn test_coerce_objects() {
+    // JSON.stringify({[key]: 0})
+    assert_eq!(
+        with_key(Point { x: 1 }, KeyPolicy::CoerceObjects).unwrap(),
+        r#"{"[object Object]":0}"#
+    );
+    assert_eq!(
+        with_key(BTreeMap::<u32, u32>::new(), KeyPolicy::CoerceObjects).unwrap(),
+        r#"{"[object Object]":0}"#
+    );
+    assert_eq!(
+        with_key(Shape::Circle(1), KeyPolicy::CoerceObjects).unwrap(),
+        r#"{"[object Object]":0}"#
+    );
+    assert_eq!(
+        with_key((1, Point { x: 1 }), KeyPolicy::CoerceObjects).unwrap(),
+        r#"{"1,[object Object]":0}"#
+    );
+    assert_eq!(with_key(1.5, KeyPolicy::CoerceObjects).unwrap(), r#"{"1.5":0}"#);
+
+    // Distinct keys collapse into one property name.
+    let mut map = BTreeMap::new();
+    map.insert(vec![Point { x: 1 }], 1);
+    map.insert(vec![Point { x: 1 }, Point { x: 2 }], 2);
+    assert_eq!(
+        to_string_with(&map, |ser| ser.set_key_policy(KeyPolicy::CoerceObjects)).unwrap(),
+        r#"{"[object Object]":1,"[object Object],[object Object]":2}"#
+    );
+}
+
+#[test]
+fn test_strict_primitives() {
+    assert_eq!(with_key(1.5, KeyPolicy::Strict).unwrap(), r#"{"1.5":0}"#);
+    assert_eq!(with_key(true, KeyPolicy::Strict).unwrap(), r#"{"true":0}"#);
+    assert_eq!(with_key(None::<u32>, KeyPolicy::Strict).unwrap(), r#"{"null":0}"#);
+    assert_eq!(with_key(Shape::Empty, KeyPolicy::Strict).unwrap(), r#"{"Empty":0}"#);
+}
+
+#[test]
+fn test_strict_errors() {
+    let err = with_key(vec![1, 2], KeyPolicy::Strict).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::KeyMustBeAString);
+    assert_eq!(err.to_string(), "key must be a string");
+    assert_eq!(err.path(), None);
+
+    assert_eq!(
+        with_key(Point { x: 1 }, KeyPolicy::Strict).unwrap_err().kind(),
+        ErrorKind::KeyMustBeAString
+    );
+    assert_eq!(
+        with_key(BTreeMap::<u32, u32>::new(), KeyPolicy::Strict).unwrap_err().kind(),
+        ErrorKind::KeyMustBeAString
+    );
+    assert_eq!(
+        with_key(Shape::Circle(1), KeyPolicy::Strict).unwrap_err().kind(),
+        ErrorKind::KeyMustBeAString
+    );
+}
+
+#[test]
+fn test_strict_error_path() {
+    let mut inner = BTreeMap::new();
+    inner.insert(vec![1, 2], 0);
+    let mut outer = BTreeMap::new();
+    outer.insert("a", vec![inner]);
+    let err = to_string_with(&outer, |ser| ser.set_key_policy(KeyPolicy::Strict)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::KeyMustBeAString);
+    assert_eq!(err.path(), Some("$.a[0]"));
+    assert_eq!(err.to_string(), "key must be a string at $.a[0]");
+
+    // Coerced, the same key is written as its joined elements.
+    assert_eq!(
+        to_string_with(&outer, |ser| ser.set_key_policy(KeyPolicy::Coerce)).unwrap(),
+        r#"{"a":[{"1,2":0}]}"#
+    );
+}
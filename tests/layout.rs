@@ -0,0 +1,132 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json_v8::ser::ErrorKind;
+
+/// Serializes like serde_json's `RawValue`, which may hold any JSON text.
+struct RawValue(&'static str);
+
+impl Serialize for RawValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        const TOKEN: &str = "$serde_json::private::RawValue";
+        let mut s = serializer.serialize_struct(TOKEN, 1)?;
+        s.serialize_field(TOKEN, self.0)?;
+        s.end()
+    }
+}
+
+#[test]
+fn test_fits() {
+    let value = json!({"a": [1, 2]});
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&value, 15).unwrap(),
+        r#"{ "a": [1, 2] }"#
+    );
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&value, 14).unwrap(),
+        "{\n  \"a\": [1, 2]\n}"
+    );
+    assert_eq!(serde_json_v8::to_string_with_width(&json!([]), 0).unwrap(), "[]");
+    assert_eq!(serde_json_v8::to_string_with_width(&json!({}), 0).unwrap(), "{}");
+}
+
+#[test]
+fn test_fill() {
+    let value = (0..12).collect::<Vec<_>>();
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&value, 20).unwrap(),
+        "[\n  0, 1, 2, 3, 4, 5,\n  6, 7, 8, 9, 10, 11\n]"
+    );
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!([-1.5, 20, -300]), 10).unwrap(),
+        "[\n  -1.5,\n  20, -300\n]"
+    );
+}
+
+#[test]
+fn test_no_fill() {
+    // A single number, and numbers mixed with other values, are not filled.
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!([123456]), 5).unwrap(),
+        "[\n  123456\n]"
+    );
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!([1, "a", 2]), 10).unwrap(),
+        "[\n  1,\n  \"a\",\n  2\n]"
+    );
+}
+
+#[test]
+fn test_must_break() {
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!([[1, 2], [3, 4]]), 80).unwrap(),
+        "[\n  [1, 2],\n  [3, 4]\n]"
+    );
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!([{"a": 1, "b": 2}, {"a": 3, "b": 4}]), 80)
+            .unwrap(),
+        "[\n  { \"a\": 1, \"b\": 2 },\n  { \"a\": 3, \"b\": 4 }\n]"
+    );
+}
+
+#[test]
+fn test_must_break_nested() {
+    // A forced break also breaks every container around it.
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!({"a": [[1, 2], [3, 4]]}), 80).unwrap(),
+        "{\n  \"a\": [\n    [1, 2],\n    [3, 4]\n  ]\n}"
+    );
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!([[[1, 2], [3, 4]]]), 80).unwrap(),
+        "[\n  [\n    [1, 2],\n    [3, 4]\n  ]\n]"
+    );
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!([1, {"b": [{"c": 1, "d": 2}, {"c": 3, "d": 4}]}]), 80)
+            .unwrap(),
+        "[\n  1,\n  {\n    \"b\": [\n      { \"c\": 1, \"d\": 2 },\n      { \"c\": 3, \"d\": 4 }\n    ]\n  }\n]"
+    );
+}
+
+#[test]
+fn test_no_must_break() {
+    // A single element, elements with one entry, and mixed kinds fit on a line.
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!([[1, 2]]), 80).unwrap(),
+        "[[1, 2]]"
+    );
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!([[1], [2, 3]]), 80).unwrap(),
+        "[[1], [2, 3]]"
+    );
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&json!([[1, 2], {"a": 1, "b": 2}]), 80).unwrap(),
+        r#"[[1, 2], { "a": 1, "b": 2 }]"#
+    );
+}
+
+#[test]
+fn test_raw_value_whitespace() {
+    let value = (RawValue("{ \"a\" : 1 }"), RawValue("[1 ,\n\t2]"), RawValue(" \"x y\" "));
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&value, 80).unwrap(),
+        r#"[{ "a": 1 }, [1, 2], "x y"]"#
+    );
+    assert_eq!(
+        serde_json_v8::to_string_with_width(&value, 10).unwrap(),
+        "[\n  {\n    \"a\": 1\n  },\n  [1, 2],\n  \"x y\"\n]"
+    );
+}
+
+#[test]
+fn test_raw_value_invalid() {
+    for &text in &["[1", "{\"a\" 1}", "\"a", "[1 2]", ""] {
+        let err = serde_json_v8::to_string_with_width(&RawValue(text), 80).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Custom, "{:?}", text);
+    }
+}
@@ -0,0 +1,82 @@
+extern crate serde_json_v8;
+
+use serde_json_v8::normalize_to_string;
+use serde_json_v8::ser::Space;
+
+#[test]
+fn test_duplicate_keys() {
+    // JSON.stringify(JSON.parse(text))
+    assert_eq!(
+        normalize_to_string("{\"a\":1,\"b\":2,\"a\":3}", 0).unwrap(),
+        "{\"a\":3,\"b\":2}"
+    );
+    assert_eq!(
+        normalize_to_string("{\"b\":1,\"a\":2,\"b\":{\"x\":1,\"x\":[2]}}", 0).unwrap(),
+        "{\"b\":{\"x\":[2]},\"a\":2}"
+    );
+    assert_eq!(
+        normalize_to_string("{\"\\u0061\":1,\"b\":2,\"a\":3}", 0).unwrap(),
+        "{\"a\":3,\"b\":2}"
+    );
+}
+
+#[test]
+fn test_array_index_keys() {
+    // 4294967294 is the largest array index, 4294967295 is a plain key.
+    assert_eq!(
+        normalize_to_string(
+            "{\"4294967295\":1,\"4294967294\":2,\"0\":3,\"01\":4,\"-0\":5,\"1.0\":6,\"a\":7,\"4294967296\":8,\"10\":9,\"9\":10}",
+            0
+        )
+        .unwrap(),
+        "{\"0\":3,\"9\":10,\"10\":9,\"4294967294\":2,\"4294967295\":1,\"01\":4,\"-0\":5,\"1.0\":6,\"a\":7,\"4294967296\":8}"
+    );
+}
+
+#[test]
+fn test_numbers() {
+    assert_eq!(
+        normalize_to_string(
+            "[1.50, -0, 1e21, 1e-7, 123456789012345678901234567890, 0.1e1, 5e-324, 1e400, -1e400, 2e-400, 100, 1E2, 0.000001]",
+            0
+        )
+        .unwrap(),
+        "[1.5,0,1e+21,1e-7,1.2345678901234568e+29,1,5e-324,null,null,0,100,100,0.000001]"
+    );
+}
+
+#[test]
+fn test_strings() {
+    assert_eq!(
+        normalize_to_string(
+            "[\"\\u0041\\/\", \"\\ud800\", \"\\udc00\\ud800\", \"\\ud83d\\ude00\", \"\\u2028\", \"\\u001f\\u007f\", \"\u{e9}\", \"\\b\\f\\n\\r\\t\"]",
+            0
+        )
+        .unwrap(),
+        "[\"A/\",\"\\ud800\",\"\\udc00\\ud800\",\"\u{1f600}\",\"\u{2028}\",\"\\u001f\u{7f}\",\"\u{e9}\",\"\\b\\f\\n\\r\\t\"]"
+    );
+}
+
+#[test]
+fn test_space() {
+    // JSON.stringify(JSON.parse(text), null, space)
+    assert_eq!(
+        normalize_to_string(" { \"a\" : [ 1 , { \"b\" : [ ] } ] , \"c\" : { } } ", 2).unwrap(),
+        "{\n  \"a\": [\n    1,\n    {\n      \"b\": []\n    }\n  ],\n  \"c\": {}\n}"
+    );
+    assert_eq!(
+        normalize_to_string("{\"a\":[1,{\"b\":[]}],\"c\":{}}", Space::from("\t")).unwrap(),
+        "{\n\t\"a\": [\n\t\t1,\n\t\t{\n\t\t\t\"b\": []\n\t\t}\n\t],\n\t\"c\": {}\n}"
+    );
+    assert_eq!(
+        normalize_to_string("[[], {}, [[]]]", 4).unwrap(),
+        "[\n    [],\n    {},\n    [\n        []\n    ]\n]"
+    );
+}
+
+#[test]
+fn test_invalid() {
+    for text in &["[1,]", "{\"a\" 1}", "", "01", "[1] 2", "'a'"] {
+        assert!(normalize_to_string(text, 0).is_err(), "{}", text);
+    }
+}
@@ -0,0 +1,84 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_json_v8;
+
+mod common;
+
+use common::to_string_with;
+use serde_json_v8::ser::{ErrorKind, NumberPolicy};
+
+fn specials() -> (f64, f64, f64, f64, f64, f32, f32) {
+    (
+        f64::NAN,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        -0.0,
+        1.5,
+        f32::NAN,
+        -0.0,
+    )
+}
+
+#[test]
+fn test_json_stringify() {
+    // JSON.stringify([NaN, Infinity, -Infinity, -0, 1.5, NaN, -0])
+    assert_eq!(
+        to_string_with(&specials(), |ser| {
+            ser.set_number_policy(NumberPolicy::JsonStringify)
+        })
+        .unwrap(),
+        "[null,null,null,0,1.5,null,0]"
+    );
+    assert_eq!(
+        serde_json_v8::to_string(&specials()).unwrap(),
+        "[null,null,null,0,1.5,null,0]"
+    );
+    // JSON.stringify({a: -0})
+    assert_eq!(
+        to_string_with(&json!({"a": -0.0}), |ser| {
+            ser.set_number_policy(NumberPolicy::JsonStringify)
+        })
+        .unwrap(),
+        r#"{"a":0}"#
+    );
+}
+
+#[test]
+fn test_preserve() {
+    assert_eq!(
+        to_string_with(&specials(), |ser| ser.set_number_policy(NumberPolicy::Preserve)).unwrap(),
+        "[NaN,Infinity,-Infinity,-0,1.5,NaN,-0]"
+    );
+}
+
+#[test]
+fn test_string() {
+    assert_eq!(
+        to_string_with(&specials(), |ser| ser.set_number_policy(NumberPolicy::String)).unwrap(),
+        r#"["NaN","Infinity","-Infinity","-0",1.5,"NaN","-0"]"#
+    );
+}
+
+#[test]
+fn test_error() {
+    assert_eq!(
+        to_string_with(&(-0.0, 1.5), |ser| ser.set_number_policy(NumberPolicy::Error)).unwrap(),
+        "[0,1.5]"
+    );
+    let err = to_string_with(&(1.5, vec![f64::NEG_INFINITY]), |ser| {
+        ser.set_number_policy(NumberPolicy::Error)
+    })
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NonFiniteNumber);
+    assert_eq!(err.path(), Some("$[1][0]"));
+    assert_eq!(err.to_string(), "-Infinity is not a finite number at $[1][0]");
+
+    let err = to_string_with(&f32::NAN, |ser| {
+        ser.set_number_policy(NumberPolicy::Error)
+    })
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NonFiniteNumber);
+    assert_eq!(err.path(), None);
+    assert_eq!(err.to_string(), "NaN is not a finite number");
+}
@@ -0,0 +1,90 @@
+extern crate serde_json_v8;
+
+use serde_json_v8::RawJson;
+
+#[test]
+fn test_accepts_primitives() {
+    for text in &[
+        "null",
+        "true",
+        "false",
+        "0",
+        "-0",
+        "1.50",
+        "1E+3",
+        "1e400",
+        "123456789012345678901234567890",
+        r#""""#,
+        r#"" padded ""#,
+        r#""\u0041\/\n""#,
+        r#""\ud800""#,
+        "\"\u{e9}\u{1f600}\"",
+    ] {
+        let raw = RawJson::new(*text).unwrap();
+        assert_eq!(raw.as_str(), *text);
+        assert_eq!(raw.to_string(), *text);
+        assert_eq!(
+            serde_json_v8::to_string(&vec![raw]).unwrap(),
+            format!("[{}]", text)
+        );
+    }
+}
+
+#[test]
+fn test_rejects_surrounding_whitespace() {
+    for text in &[" 1", "1 ", "\n1", "1\n", "\t\"a\"", "\"a\"\r", " null "] {
+        assert!(RawJson::new(*text).is_err(), "{:?}", text);
+    }
+}
+
+#[test]
+fn test_rejects_non_primitives() {
+    for text in &["[]", "[1]", "{}", r#"{"a":1}"#] {
+        assert!(RawJson::new(*text).is_err(), "{:?}", text);
+    }
+}
+
+#[test]
+fn test_rejects_invalid_json() {
+    for text in &[
+        "",
+        "tru",
+        "True",
+        "nul",
+        "undefined",
+        "NaN",
+        "Infinity",
+        "01",
+        "1.",
+        ".5",
+        "+1",
+        "1e",
+        "'a'",
+        "\"unterminated",
+        "\"\\x\"",
+        "\"\\u12\"",
+        "\"a\u{1}\"",
+        "1 2",
+        "\"a\"\"b\"",
+        "null,",
+    ] {
+        assert!(RawJson::new(*text).is_err(), "{:?}", text);
+    }
+}
+
+#[test]
+fn test_error_message() {
+    assert_eq!(
+        RawJson::new("[1]").unwrap_err().to_string(),
+        "raw JSON must be a single primitive value without surrounding whitespace"
+    );
+    assert_eq!(
+        RawJson::new(" 1").unwrap_err().to_string(),
+        "raw JSON must be a single primitive value without surrounding whitespace"
+    );
+}
+
+#[test]
+fn test_into_string() {
+    assert_eq!(RawJson::new("1.0").unwrap().into_string(), "1.0");
+}
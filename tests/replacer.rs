@@ -0,0 +1,138 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use serde_json::Value;
+use serde_json_v8::ser::{KeyOrder, NumberPolicy, Replacer, Serializer};
+
+#[derive(Serialize)]
+struct Account {
+    zeta: u32,
+    alpha: &'static str,
+    password: &'static str,
+}
+
+fn account() -> Account {
+    Account {
+        zeta: 1,
+        alpha: "ann",
+        password: "hunter2",
+    }
+}
+
+#[test]
+fn test_identity_keeps_field_order() {
+    let identity = Replacer::function(|_, value| Some(value));
+    assert_eq!(
+        serde_json_v8::to_string_with_replacer(&account(), identity).unwrap(),
+        Some(r#"{"zeta":1,"alpha":"ann","password":"hunter2"}"#.to_owned())
+    );
+}
+
+#[test]
+fn test_function_visit_order() {
+    let mut paths = Vec::new();
+    {
+        let record = Replacer::function(|path, value| {
+            paths.push(path.join("."));
+            Some(value)
+        });
+        serde_json_v8::to_string_with_replacer(&json!({"a": [1, {"b": 2}]}), record).unwrap();
+    }
+    assert_eq!(paths, ["", "a", "a.0", "a.1", "a.1.b"]);
+}
+
+#[test]
+fn test_function_drop() {
+    // JSON.stringify({zeta: 1, alpha: "ann", password: "hunter2"}, (k, v) => k === "password" ? undefined : v)
+    let redact = Replacer::function(|path, value| match path.last() {
+        Some(key) if key == "password" => None,
+        _ => Some(value),
+    });
+    assert_eq!(
+        serde_json_v8::to_string_with_replacer(&account(), redact).unwrap(),
+        Some(r#"{"zeta":1,"alpha":"ann"}"#.to_owned())
+    );
+
+    // JSON.stringify([1, 2], (k, v) => k === "0" ? undefined : v)
+    let drop_first = Replacer::function(|path, value| match path.last() {
+        Some(key) if key == "0" => None,
+        _ => Some(value),
+    });
+    assert_eq!(
+        serde_json_v8::to_string_with_replacer(&json!([1, 2]), drop_first).unwrap(),
+        Some("[null,2]".to_owned())
+    );
+
+    // JSON.stringify(1, () => undefined)
+    let drop_all = Replacer::function(|_, _| None);
+    assert_eq!(
+        serde_json_v8::to_string_with_replacer(&1, drop_all).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_function_replace() {
+    // JSON.stringify({zeta: 1, alpha: "ann", password: "hunter2"}, (k, v) => k === "zeta" ? {n: v} : v)
+    let wrap = Replacer::function(|path, value| match path.last() {
+        Some(key) if key == "zeta" => Some(json!({ "n": value })),
+        _ => Some(value),
+    });
+    assert_eq!(
+        serde_json_v8::to_string_with_replacer(&account(), wrap).unwrap(),
+        Some(r#"{"zeta":{"n":1},"alpha":"ann","password":"hunter2"}"#.to_owned())
+    );
+}
+
+#[test]
+fn test_allowlist_order() {
+    // JSON.stringify({zeta: 1, alpha: "ann", password: "hunter2"}, ["alpha", "missing", "zeta", "alpha"])
+    let allow = Replacer::allowlist(vec!["alpha", "missing", "zeta", "alpha"]);
+    assert_eq!(
+        serde_json_v8::to_string_with_replacer(&account(), allow).unwrap(),
+        Some(r#"{"alpha":"ann","zeta":1}"#.to_owned())
+    );
+}
+
+#[derive(Serialize)]
+struct Indexed {
+    b: u32,
+    #[serde(rename = "1")]
+    one: u32,
+}
+
+#[test]
+fn test_serializer_settings() {
+    let mut ser = Serializer::new(Vec::new());
+    ser.set_number_policy(NumberPolicy::Preserve);
+    ser.set_key_order(KeyOrder::V8);
+    let identity = &mut Replacer::function(|_, value: Value| Some(value));
+    let value = (f64::NAN, Indexed { b: 1, one: 2 });
+    assert!(ser.serialize_with_replacer(&value, identity).unwrap());
+    assert_eq!(
+        String::from_utf8(ser.into_inner()).unwrap(),
+        r#"[NaN,{"1":2,"b":1}]"#
+    );
+}
+
+#[test]
+fn test_function_replace_keeps_unchanged_parts() {
+    let mut ser = Serializer::new(Vec::new());
+    ser.set_number_policy(NumberPolicy::Preserve);
+    // JSON.stringify([NaN, {zeta: 1, alpha: "ann", password: "hunter2"}], (k, v) => k === "1" ? {...v, added: true} : v)
+    let add = &mut Replacer::function(|path, mut value: Value| {
+        if path == ["1"] {
+            value["added"] = Value::Bool(true);
+        }
+        Some(value)
+    });
+    assert!(ser.serialize_with_replacer(&(f64::NAN, account()), add).unwrap());
+    assert_eq!(
+        String::from_utf8(ser.into_inner()).unwrap(),
+        r#"[NaN,{"zeta":1,"alpha":"ann","password":"hunter2","added":true}]"#
+    );
+}
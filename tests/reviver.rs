@@ -0,0 +1,104 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use serde_json::Value;
+
+#[test]
+fn test_visit_order() {
+    // JSON.parse(text, function (k, v) { keys.push(k); return v; })
+    let text = r#"{"b": {"x": 1}, "2": [true, null], "a": "s", "1": 0}"#;
+    let mut keys = Vec::new();
+    serde_json_v8::from_str_with_reviver(text, |key, value, _| {
+        keys.push(key.to_owned());
+        Some(value)
+    })
+    .unwrap();
+    assert_eq!(keys, ["1", "0", "1", "2", "x", "b", "a", ""]);
+}
+
+#[test]
+fn test_duplicate_keys() {
+    let mut visits = Vec::new();
+    let value = serde_json_v8::from_str_with_reviver(r#"{"a": 1, "b": 2, "a": 3}"#, |key, value, _| {
+        visits.push((key.to_owned(), value.clone()));
+        Some(value)
+    })
+    .unwrap();
+    assert_eq!(
+        visits,
+        [
+            ("a".to_owned(), json!(3)),
+            ("b".to_owned(), json!(2)),
+            ("".to_owned(), json!({"a": 3, "b": 2})),
+        ]
+    );
+    assert_eq!(value, Some(json!({"a": 3, "b": 2})));
+}
+
+#[test]
+fn test_delete() {
+    // JSON.parse(text, (k, v) => k === "drop" || k === "1" ? undefined : v)
+    let text = r#"{"keep": 1, "drop": [1, 2], "list": [1, 2, 3]}"#;
+    let value = serde_json_v8::from_str_with_reviver(text, |key, value, _| match key {
+        "drop" | "1" => None,
+        _ => Some(value),
+    })
+    .unwrap();
+    assert_eq!(value, Some(json!({"keep": 1, "list": [1, null, 3]})));
+
+    let value = serde_json_v8::from_str_with_reviver("[1]", |key, value, _| match key {
+        "" => None,
+        _ => Some(value),
+    })
+    .unwrap();
+    assert_eq!(value, None);
+}
+
+#[test]
+fn test_infinity() {
+    let mut sources = Vec::new();
+    let value = serde_json_v8::from_str_with_reviver("[1e400, -1e400, 1e-400]", |key, value, context| {
+        if !key.is_empty() {
+            sources.push((value.clone(), context.source().unwrap().to_owned()));
+        }
+        Some(value)
+    })
+    .unwrap();
+    // JSON.stringify(JSON.parse("[1e400, -1e400, 1e-400]"))
+    assert_eq!(serde_json_v8::to_string(&value).unwrap(), "[null,null,0]");
+    assert_eq!(
+        sources,
+        [
+            (Value::Null, "1e400".to_owned()),
+            (Value::Null, "-1e400".to_owned()),
+            (json!(0.0), "1e-400".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn test_lone_surrogate() {
+    let mut strings = Vec::new();
+    let value = serde_json_v8::from_str_with_reviver(r#"["\ud800", "ab"]"#, |key, value, context| {
+        if !key.is_empty() {
+            strings.push(context.js_string().unwrap().into_utf16());
+        }
+        Some(value)
+    })
+    .unwrap();
+    assert_eq!(value, Some(json!(["\u{fffd}", "ab"])));
+    assert_eq!(strings, [vec![0xd800], vec![0x61, 0x62]]);
+}
+
+#[test]
+fn test_syntax_error_before_reviver() {
+    let mut calls = 0;
+    let result = serde_json_v8::from_str_with_reviver("[1, 2,]", |_, value, _| {
+        calls += 1;
+        Some(value)
+    });
+    assert!(result.is_err());
+    assert_eq!(calls, 0);
+}
@@ -0,0 +1,77 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use serde::Serialize;
+use serde_json::Value;
+use serde_json_v8::ser::{PrettyV8Formatter, Serializer, Space};
+
+fn to_string_with(value: &Value, formatter: PrettyV8Formatter) -> String {
+    let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    value.serialize(&mut ser).unwrap();
+    String::from_utf8(ser.into_inner()).unwrap()
+}
+
+#[test]
+fn test_empty_indent_keeps_newlines() {
+    let value = json!({"a": [1]});
+    assert_eq!(
+        to_string_with(&value, PrettyV8Formatter::with_indent(b"")),
+        "{\n\"a\": [\n1\n]\n}"
+    );
+}
+
+#[test]
+fn test_empty_space_is_compact() {
+    let value = json!({"a": [1]});
+    // JSON.stringify({a: [1]}, null, "")
+    assert_eq!(
+        to_string_with(&value, PrettyV8Formatter::with_space(Space::from(""))),
+        r#"{"a":[1]}"#
+    );
+    // JSON.stringify({a: [1]}, null, 0)
+    assert_eq!(
+        to_string_with(&value, PrettyV8Formatter::with_space(Space::from(0))),
+        r#"{"a":[1]}"#
+    );
+    // JSON.stringify({a: [1]}, null, NaN)
+    assert_eq!(
+        serde_json_v8::to_string_with_space(&value, f64::NAN).unwrap(),
+        r#"{"a":[1]}"#
+    );
+}
+
+#[test]
+fn test_space_number() {
+    let value = json!({"a": [1]});
+    // JSON.stringify({a: [1]}, null, 3)
+    assert_eq!(
+        serde_json_v8::to_string_with_space(&value, 3).unwrap(),
+        "{\n   \"a\": [\n      1\n   ]\n}"
+    );
+    // JSON.stringify([1], null, 20)
+    assert_eq!(
+        serde_json_v8::to_string_with_space(&json!([1]), 20).unwrap(),
+        "[\n          1\n]"
+    );
+    // JSON.stringify([1], null, 2.9)
+    assert_eq!(
+        serde_json_v8::to_string_with_space(&json!([1]), 2.9).unwrap(),
+        "[\n  1\n]"
+    );
+}
+
+#[test]
+fn test_space_string() {
+    // JSON.stringify([1], null, "abcdefghijkl")
+    assert_eq!(
+        serde_json_v8::to_string_with_space(&json!([1]), "abcdefghijkl").unwrap(),
+        "[\nabcdefghij1\n]"
+    );
+    // JSON.stringify([[], {}], null, "\t")
+    assert_eq!(
+        serde_json_v8::to_string_with_space(&json!([[], {}]), "\t").unwrap(),
+        "[\n\t[],\n\t{}\n]"
+    );
+}
@@ -0,0 +1,159 @@
+extern crate serde;
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use serde_json::Value;
+use serde_json_v8::syntax::{MessageVersion, ParseError};
+
+fn parse_error(text: &str) -> ParseError {
+    ParseError::new(serde_json_v8::from_str::<Value>(text).unwrap_err(), text)
+}
+
+fn contextual(text: &str) -> String {
+    parse_error(text).message(MessageVersion::Contextual)
+}
+
+#[test]
+fn test_contextual_reasons() {
+    // JSON.parse(text) in Node.js 20
+    let cases = [
+        ("{\"a\":1,}", "Expected double-quoted property name in JSON at position 7"),
+        ("[1", "Expected ',' or ']' after array element in JSON at position 2"),
+        ("{", "Expected property name or '}' in JSON at position 1"),
+        ("{a:1}", "Expected property name or '}' in JSON at position 1"),
+        ("{\"a\"", "Expected ':' after property name in JSON at position 4"),
+        ("{\"a\":1 \"b\":2}", "Expected ',' or '}' after property value in JSON at position 7"),
+        ("[1 2]", "Expected ',' or ']' after array element in JSON at position 3"),
+        ("[1] x", "Unexpected non-whitespace character after JSON at position 4"),
+        ("[1] \"a\"", "Unexpected non-whitespace character after JSON at position 4"),
+        ("{\"a\":1}}", "Unexpected non-whitespace character after JSON at position 7"),
+        ("\"a\u{1}\"", "Bad control character in string literal in JSON at position 2"),
+        ("\"abc", "Unterminated string in JSON at position 4"),
+        ("\"\\x\"", "Bad escaped character in JSON at position 2"),
+        ("\"\\u12g4\"", "Bad Unicode escape in JSON at position 5"),
+        ("-", "No number after minus sign in JSON at position 1"),
+        ("1.", "Unterminated fractional number in JSON at position 2"),
+        ("1e", "Exponent part is missing a number in JSON at position 2"),
+        ("1e+", "Exponent part is missing a number in JSON at position 3"),
+    ];
+    for &(text, expected) in &cases {
+        assert_eq!(contextual(text), expected, "{}", text);
+    }
+}
+
+#[test]
+fn test_contextual_tokens() {
+    // JSON.parse(text) in Node.js 20
+    let cases = [
+        ("", "Unexpected end of JSON input"),
+        ("tru", "Unexpected end of JSON input"),
+        ("01", "Unexpected number in JSON at position 1"),
+        ("[1, 2,]", "Unexpected token ']', \"[1, 2,]\" is not valid JSON"),
+        ("'a'", "Unexpected token ''', \"'a'\" is not valid JSON"),
+        ("[object Object]", "\"[object Object]\" is not valid JSON"),
+        ("undefined", "\"undefined\" is not valid JSON"),
+        ("NaN", "\"NaN\" is not valid JSON"),
+    ];
+    for &(text, expected) in &cases {
+        assert_eq!(contextual(text), expected, "{}", text);
+    }
+}
+
+#[test]
+fn test_contextual_quotes() {
+    // JSON.parse(text) in Node.js 20
+    let cases = [
+        (
+            "[1, 2, 3, 4, 5, 6, 7, 8, x]",
+            "Unexpected token 'x', ...\" 6, 7, 8, x]\" is not valid JSON",
+        ),
+        (
+            "[x, 1, 2, 3, 4, 5, 6, 7, 8]",
+            "Unexpected token 'x', \"[x, 1, 2, 3\"... is not valid JSON",
+        ),
+        (
+            "[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, x, 13, 14, 15, 16, 17]",
+            "Unexpected token 'x', ...\", 11, 12, x, 13, 14,\"... is not valid JSON",
+        ),
+        (
+            "[\"\u{1f600}\", x]",
+            "Unexpected token 'x', \"[\"\u{1f600}\", x]\" is not valid JSON",
+        ),
+    ];
+    for &(text, expected) in &cases {
+        assert_eq!(contextual(text), expected, "{}", text);
+    }
+}
+
+#[test]
+fn test_legacy() {
+    // JSON.parse(text) in Node.js 18
+    let cases = [
+        ("{\"a\":1,}", "Unexpected token } in JSON at position 7"),
+        ("[1, 2,]", "Unexpected token ] in JSON at position 6"),
+        ("{a:1}", "Unexpected token a in JSON at position 1"),
+        ("[1] x", "Unexpected token x in JSON at position 4"),
+        ("[1] \"a\"", "Unexpected string in JSON at position 4"),
+        ("[1 2]", "Unexpected number in JSON at position 3"),
+        ("", "Unexpected end of JSON input"),
+        ("[1", "Unexpected end of JSON input"),
+        ("\"abc", "Unexpected end of JSON input"),
+    ];
+    for &(text, expected) in &cases {
+        assert_eq!(parse_error(text).message(MessageVersion::Legacy), expected, "{}", text);
+    }
+}
+
+#[test]
+fn test_line_column() {
+    // JSON.parse(text) in Node.js 22
+    let cases = [
+        (
+            "{\"a\":1,}",
+            "Expected double-quoted property name in JSON at position 7 (line 1 column 8)",
+        ),
+        (
+            "[1,\n 2\n x]",
+            "Expected ',' or ']' after array element in JSON at position 8 (line 3 column 2)",
+        ),
+        ("", "Unexpected end of JSON input"),
+        ("[1, 2,]", "Unexpected token ']', \"[1, 2,]\" is not valid JSON"),
+    ];
+    for &(text, expected) in &cases {
+        let error = parse_error(text);
+        assert_eq!(error.message(MessageVersion::LineColumn), expected, "{}", text);
+        assert_eq!(error.to_string(), expected, "{}", text);
+    }
+}
+
+#[test]
+fn test_utf16_position() {
+    let error = parse_error("[\"\u{1f600}\", x]");
+    assert_eq!(error.position(), Some(7));
+    assert_eq!(error.line(), Some(1));
+    assert_eq!(error.column(), Some(8));
+
+    // `\r\n` is one line break.
+    let error = parse_error("[\"\u{e9}\u{1f600}\",\n \"b\",\r\n x]");
+    assert_eq!(error.position(), Some(16));
+    assert_eq!(error.line(), Some(3));
+    assert_eq!(error.column(), Some(2));
+
+    let error = parse_error("\"\u{1f600}");
+    assert_eq!(error.position(), Some(3));
+    assert_eq!(
+        error.message(MessageVersion::LineColumn),
+        "Unterminated string in JSON at position 3 (line 1 column 4)"
+    );
+}
+
+#[test]
+fn test_not_a_syntax_error() {
+    let text = "[1]";
+    let inner = serde_json_v8::from_str::<String>(text).unwrap_err();
+    let message = inner.to_string();
+    let error = ParseError::new(inner, text);
+    assert_eq!(error.position(), None);
+    assert_eq!(error.message(MessageVersion::Contextual), message);
+    assert_eq!(error.to_string(), message);
+}
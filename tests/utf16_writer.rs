@@ -0,0 +1,57 @@
+extern crate serde;
+extern crate serde_json;
+extern crate serde_json_v8;
+
+use std::io::{ErrorKind, Write};
+
+use serde_json_v8::ser::Utf16Writer;
+use serde_json_v8::JsString;
+
+fn utf16(s: &str) -> Vec<u16> {
+    s.encode_utf16().collect()
+}
+
+#[test]
+fn test_surrogate_pair_split_across_writes() {
+    let bytes = "a😀b".as_bytes();
+    for split in 1..bytes.len() {
+        let mut units = Vec::new();
+        {
+            let mut writer = Utf16Writer::new(&mut units);
+            writer.write_all(&bytes[..split]).unwrap();
+            writer.write_all(&bytes[split..]).unwrap();
+        }
+        assert_eq!(units, [0x61, 0xd83d, 0xde00, 0x62], "split at {}", split);
+    }
+}
+
+#[test]
+fn test_byte_by_byte() {
+    let text = "é€😀\u{10ffff}";
+    let mut units = Vec::new();
+    {
+        let mut writer = Utf16Writer::new(&mut units);
+        for byte in text.bytes() {
+            writer.write_all(&[byte]).unwrap();
+        }
+    }
+    assert_eq!(units, utf16(text));
+}
+
+#[test]
+fn test_invalid_utf8() {
+    let mut units = Vec::new();
+    let mut writer = Utf16Writer::new(&mut units);
+    let err = writer.write_all(b"a\xffb").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_to_utf16() {
+    let value = (JsString::from(vec![0xd83d]), "😀");
+    // JSON.stringify(["\ud83d", "😀"])
+    assert_eq!(
+        serde_json_v8::to_utf16(&value).unwrap(),
+        utf16("[\"\\ud83d\",\"\u{1f600}\"]")
+    );
+}